@@ -1,23 +1,43 @@
-use anchor_lang::prelude::*;
-
 pub const ITERATION_TAG: &[u8]   = b"ITERATION";
 pub const BUYER_TAG: &[u8]       = b"BUYER";
 pub const ADVISER_TAG: &[u8]     = b"ADVISER";
-pub const STORE: &str            = ""; // TODO: change
+pub const VAULT_TAG: &[u8]       = b"VAULT";
+pub const CONFIG_TAG: &[u8]      = b"CONFIG";
+pub const MINT_TAG: &[u8]        = b"MINT";
+pub const REWARD_TAG: &[u8]      = b"REWARD";
+pub const ATTESTATION_TAG: &[u8] = b"ATTESTATION";
+pub const ESCROW_TAG: &[u8]      = b"ESCROW";
+pub const CONTRIBUTION_TAG: &[u8] = b"CONTRIBUTION";
+pub const BLOCKLIST_TAG: &[u8]   = b"BLOCKLIST";
+
+pub const ROLE_PRESALE_MANAGER: u8   = 1 << 0;
+pub const ROLE_ITERATION_MANAGER: u8 = 1 << 1;
+pub const ROLE_ADVISER_MANAGER: u8   = 1 << 2;
+pub const ROLE_TREASURY_MANAGER: u8  = 1 << 3;
+pub const ROLE_PAUSER: u8            = 1 << 4;
+pub const ROLE_PRICE_ATTESTER: u8    = 1 << 5;
+pub const ROLE_COMPLIANCE: u8        = 1 << 6;
+pub const ALL_ROLES: u8              = (1 << 7) - 1;
+
+pub const PAUSE_BUYS: u8             = 1 << 0;
+pub const PAUSE_ADVISER_CLAIMS: u8   = 1 << 1;
+pub const PAUSE_TOKEN_CLAIMS: u8     = 1 << 2;
+pub const ALL_PAUSE_FLAGS: u8        = (1 << 3) - 1;
 
-pub const SOL_USD_PRICEFEED: &str   = "7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE";
 pub const FEED_ID: &str = "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
-pub const FEED_MAX_AGE: u64 = 36000; // 10 hours
+pub const FEED_MAX_AGE: u64 = 60; // 1 minute
+pub const FEED_MAX_CONF_RATIO: u64 = 20_000_000; // 2%
+pub const FEED_MAX_DIVERGENCE: u64 = 50_000_000; // 5%
+pub const QUOTE_MAX_BAND: u64 = 10_000_000; // 1%
 
-pub const PRECISION: u32            = 9;
-pub const STABLE_PRECISION: u32     = 3;
+pub const QUOTE_DOMAIN: &[u8] = b"presale:quote:";
+pub const KYC_DOMAIN: &[u8]   = b"presale:kyc:";
 
-pub const USDT: &str                = ""; // TODO: change
-pub const USDC: &str                = ""; // TODO: change
+pub const BUYER_VERSION: u8 = 3;
+pub const MAX_BUYER_CURRENCIES: usize = 4;
+pub const MAX_BUYER_ITERATIONS: usize = 16;
 
-pub const SIGNATURE_SIGNER: &str     = ""; // TODO: change
-const OWNERS: &[&str] = &[""]; // TODO: change
+pub const KYC_TIERS: usize = 4;
+pub const MAX_BONUS_TIERS: usize = 5;
 
-pub fn only_owners(address: Pubkey) -> bool {
-  return OWNERS.contains(&address.to_string().as_str());
-}
+pub const PRECISION: u32            = 9;
@@ -30,8 +30,8 @@ pub enum Presale {
   InactiveIteration,
   #[msg("Wrong price feed account")]
   WrongPriceFeedId,
-  #[msg("Wrong stablecoin account")]
-  WrongStablecoin,
+  #[msg("Wrong mint account")]
+  WrongMint,
   #[msg("Wrong store address")]
   WrongStore,
   #[msg("Oracle price is down")]
@@ -40,4 +40,142 @@ pub enum Presale {
   AdviserNoFunds,
   #[msg("Expired signature")]
   ExpiredSignature,
+  #[msg("Token mint not set")]
+  TokenMintNotSet,
+  #[msg("Token claims not enabled")]
+  ClaimsNotEnabled,
+  #[msg("Token claims already enabled")]
+  ClaimsEnabled,
+  #[msg("Buyer no funds")]
+  BuyerNoFunds,
+  #[msg("Vesting tge percent too large")]
+  VestingPercentTooLarge,
+  #[msg("Invalid vesting schedule")]
+  InvalidVestingSchedule,
+  #[msg("Admin already exists")]
+  AdminExists,
+  #[msg("Admin not found")]
+  AdminNotFound,
+  #[msg("Too many admins")]
+  TooManyAdmins,
+  #[msg("No pending owner")]
+  NoPendingOwner,
+  #[msg("Unknown role")]
+  UnknownRole,
+  #[msg("Mint not accepted")]
+  MintNotAccepted,
+  #[msg("Price feed account missing")]
+  MissingPriceFeed,
+  #[msg("Oracle price is too old")]
+  PriceTooOld,
+  #[msg("Oracle price confidence is too wide")]
+  PriceConfidenceTooWide,
+  #[msg("Oracle price is not fully verified")]
+  PriceNotVerified,
+  #[msg("Invalid oracle policy")]
+  InvalidOraclePolicy,
+  #[msg("Invalid depeg threshold")]
+  InvalidDepegThreshold,
+  #[msg("Mint is trading below its peg")]
+  MintDepegged,
+  #[msg("Oracle price sources diverge")]
+  PriceSourcesDiverge,
+  #[msg("Invalid price attestation")]
+  InvalidPriceAttestation,
+  #[msg("Quote expired")]
+  QuoteExpired,
+  #[msg("Quote nonce already used")]
+  QuoteNonceUsed,
+  #[msg("Quote price is outside of the oracle band")]
+  QuoteOutOfBand,
+  #[msg("Presale not paused")]
+  PresaleNotPaused,
+  #[msg("Unknown pause flag")]
+  UnknownPauseFlag,
+  #[msg("Buys are paused")]
+  BuysPaused,
+  #[msg("Adviser claims are paused")]
+  AdviserClaimsPaused,
+  #[msg("Token claims are paused")]
+  TokenClaimsPaused,
+  #[msg("Invalid iteration schedule")]
+  InvalidIterationSchedule,
+  #[msg("Iteration not started")]
+  IterationNotStarted,
+  #[msg("Invalid iteration chain")]
+  InvalidIterationChain,
+  #[msg("Iteration neither sold out nor ended")]
+  IterationNotFinished,
+  #[msg("Hard cap is below the sold supply")]
+  HardCapTooSmall,
+  #[msg("Presale hard cap exceeded")]
+  HardCapExceeded,
+  #[msg("Escrow account missing")]
+  EscrowAccountMissing,
+  #[msg("Presale soft cap not reached")]
+  SoftCapNotReached,
+  #[msg("Escrow not released")]
+  EscrowNotReleased,
+  #[msg("Presale not refundable")]
+  NotRefundable,
+  #[msg("Buyer already migrated")]
+  BuyerAlreadyMigrated,
+  #[msg("Buyer ledger is full")]
+  BuyerLedgerFull,
+  #[msg("Invalid buy limits")]
+  InvalidBuyLimits,
+  #[msg("Presale transaction limit exceeded")]
+  PresaleMaxBuyExceeded,
+  #[msg("Wallet limit exceeded")]
+  WalletMaxBuyExceeded,
+  #[msg("Iteration min buy not reached")]
+  IterationMinBuyNotReached,
+  #[msg("Iteration transaction limit exceeded")]
+  IterationMaxBuyExceeded,
+  #[msg("Iteration has no allowlist")]
+  IterationNotGated,
+  #[msg("Invalid allowlist proof")]
+  InvalidAllowlistProof,
+  #[msg("Wallet not allowlisted")]
+  NotAllowlisted,
+  #[msg("Allocation exceeded")]
+  AllocationExceeded,
+  #[msg("Invalid KYC tier")]
+  InvalidKycTier,
+  #[msg("KYC pass expired")]
+  KycPassExpired,
+  #[msg("KYC tier limit exceeded")]
+  KycLimitExceeded,
+  #[msg("Wallet is blocked")]
+  WalletBlocked,
+  #[msg("Invalid bonus percent")]
+  InvalidBonusPercent,
+  #[msg("Too many bonus tiers")]
+  TooManyBonusTiers,
+  #[msg("Bonus tier not found")]
+  BonusTierNotFound,
+  #[msg("Invalid buyer discount")]
+  InvalidBuyerDiscount,
+  #[msg("Presale is paused")]
+  PresalePaused,
+  #[msg("KYC signer is not set")]
+  KycSignerNotSet,
+  #[msg("KYC pass is stale")]
+  KycPassStale,
+  #[msg("Buyer USD amount is not backfilled")]
+  BuyerUsdNotBackfilled,
+  #[msg("Buyer USD amount is already backfilled")]
+  BuyerUsdAlreadyBackfilled,
+  #[msg("Adviser code is not active")]
+  AdviserNotActive,
+  #[msg("Fresh price attestation required")]
+  PriceAttestationRequired,
+  #[msg("Nothing to settle")]
+  NothingToSettle,
+  #[msg("Account already migrated")]
+  AccountAlreadyMigrated,
+  #[msg("Adviser not migrated")]
+  AdviserNotMigrated,
+  #[msg("Escrow already refunded")]
+  EscrowRefunded,
 }
@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use crate::state::presale::LeftoverPolicy;
 
 #[event]
 pub struct BoughtWithSol {
@@ -7,40 +8,217 @@ pub struct BoughtWithSol {
   pub adviser: String,
   pub amount: u64,
   pub token_amount: u128,
+  pub bonus_amount: u128,
+  pub discount_amount: u128,
+  /// Tokens credited in `iteration`, including bonus
+  pub iteration_token_amount: u128,
+  /// Iteration the buy spilled into once `iteration` sold out, -1 when it did not
+  pub next_iteration: i16,
+  pub next_iteration_token_amount: u128,
 }
 
 #[event]
-pub struct BoughtWithUsdt {
+pub struct BoughtWithToken {
   pub iteration: i16,
   pub buyer: Pubkey,
   pub adviser: String,
+  pub mint: Pubkey,
   pub amount: u64,
   pub token_amount: u128,
+  pub bonus_amount: u128,
+  pub discount_amount: u128,
+  /// Tokens credited in `iteration`, including bonus
+  pub iteration_token_amount: u128,
+  /// Iteration the buy spilled into once `iteration` sold out, -1 when it did not
+  pub next_iteration: i16,
+  pub next_iteration_token_amount: u128,
 }
 
 #[event]
-pub struct BoughtWithUsdc {
-  pub iteration: i16,
-  pub buyer: Pubkey,
-  pub adviser: String,
+pub struct ClaimedSol {
+  pub code: String,
   pub amount: u64,
-  pub token_amount: u128,
 }
 
 #[event]
-pub struct ClaimedSol {
+pub struct ClaimedReward {
   pub code: String,
+  pub mint: Pubkey,
   pub amount: u64,
 }
 
 #[event]
-pub struct ClaimedUsdc {
+pub struct ClaimedToken {
   pub code: String,
   pub amount: u64,
 }
 
 #[event]
-pub struct ClaimedUsdt {
-  pub code: String,
+pub struct TokensClaimed {
+  pub buyer: Pubkey,
   pub amount: u64,
 }
+
+#[event]
+pub struct AdminAdded {
+  pub admin: Pubkey,
+  pub roles: u8,
+}
+
+#[event]
+pub struct PriceAttested {
+  pub feed_id: [u8; 32],
+  pub price: u64,
+  pub expo: u32,
+  pub publish_time: i64,
+  pub attester: Pubkey,
+}
+
+#[event]
+pub struct QuoteFilled {
+  pub buyer: Pubkey,
+  pub price: u64,
+  pub nonce: u64,
+}
+
+#[event]
+pub struct IterationAdvanced {
+  pub previous: i16,
+  pub next: i16,
+  pub rolled_over: u128,
+}
+
+#[event]
+pub struct LeftoverSettled {
+  pub iteration: i16,
+  pub policy: LeftoverPolicy,
+  pub amount: u128,
+}
+
+#[event]
+pub struct ReserveWithdrawn {
+  pub amount: u128,
+  pub store: Pubkey,
+}
+
+#[event]
+pub struct LeftoverBurned {
+  pub amount: u128,
+}
+
+#[event]
+pub struct EscrowReleased {
+  pub mint: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct Refunded {
+  pub buyer: Pubkey,
+  pub mint: Pubkey,
+  pub amount: u64,
+  pub token_amount: u128,
+}
+
+#[event]
+pub struct PresalePaused {}
+
+#[event]
+pub struct PresaleUnpaused {}
+
+#[event]
+pub struct SubsystemsPaused {
+  pub flags: u8,
+}
+
+#[event]
+pub struct SubsystemsUnpaused {
+  pub flags: u8,
+}
+
+#[event]
+pub struct AdminRemoved {
+  pub admin: Pubkey,
+}
+
+#[event]
+pub struct AllocationRegistered {
+  pub buyer: Pubkey,
+  pub iteration: i16,
+  pub allocation: u128,
+}
+
+#[event]
+pub struct KycPassVerified {
+  pub buyer: Pubkey,
+  pub tier: u8,
+  pub expiry: i64,
+  pub issued_at: i64,
+}
+
+#[event]
+pub struct PresaleMigrated {
+  pub presale: Pubkey,
+}
+
+#[event]
+pub struct IterationMigrated {
+  pub iteration: i16,
+}
+
+#[event]
+pub struct AdviserMigrated {
+  pub code: String,
+  pub usdt_reward: u64,
+  pub usdc_reward: u64,
+}
+
+#[event]
+pub struct BuyerMigrated {
+  pub buyer: Pubkey,
+  pub version: u8,
+  pub usd_pending: bool,
+}
+
+#[event]
+pub struct BuyerUsdBackfilled {
+  pub buyer: Pubkey,
+  pub usd_amount: u128,
+  pub admin: Pubkey,
+}
+
+#[event]
+pub struct WalletBlocked {
+  pub wallet: Pubkey,
+  pub admin: Pubkey,
+}
+
+#[event]
+pub struct WalletUnblocked {
+  pub wallet: Pubkey,
+  pub admin: Pubkey,
+}
+
+#[event]
+pub struct RolesGranted {
+  pub admin: Pubkey,
+  pub roles: u8,
+}
+
+#[event]
+pub struct RolesRevoked {
+  pub admin: Pubkey,
+  pub roles: u8,
+}
+
+#[event]
+pub struct OwnershipTransferStarted {
+  pub owner: Pubkey,
+  pub pending_owner: Pubkey,
+}
+
+#[event]
+pub struct OwnershipTransferred {
+  pub previous_owner: Pubkey,
+  pub owner: Pubkey,
+}
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::errors;
+use crate::state::accepted_mint::*;
+use crate::state::config::Config;
+
+use crate::config::{ MINT_TAG, CONFIG_TAG, ROLE_TREASURY_MANAGER };
+
+pub fn add_accepted_mint(
+  ctx: Context<AddAcceptedMint>,
+  price_source: PriceSource,
+  feed_id: [u8; 32],
+) -> Result<()> {
+  let mint = &ctx.accounts.mint;
+  let accepted_mint = &mut ctx.accounts.accepted_mint;
+  accepted_mint.init(mint.key(), mint.decimals, price_source, feed_id)
+}
+
+pub fn set_accepted_mint_price_source(
+  ctx: Context<SetAcceptedMintPriceSource>,
+  price_source: PriceSource,
+  feed_id: [u8; 32],
+) -> Result<()> {
+  let accepted_mint = &mut ctx.accounts.accepted_mint;
+  accepted_mint.set_price_source(price_source, feed_id)
+}
+
+pub fn set_accepted_mint_depeg_policy(
+  ctx: Context<SetAcceptedMintDepegPolicy>,
+  depeg_threshold: u64,
+  depeg_policy: DepegPolicy,
+) -> Result<()> {
+  let accepted_mint = &mut ctx.accounts.accepted_mint;
+  accepted_mint.set_depeg_policy(depeg_threshold, depeg_policy)
+}
+
+pub fn enable_accepted_mint(
+  ctx: Context<SetAcceptedMintEnabled>,
+) -> Result<()> {
+  let accepted_mint = &mut ctx.accounts.accepted_mint;
+  accepted_mint.enable()
+}
+
+pub fn disable_accepted_mint(
+  ctx: Context<SetAcceptedMintDisabled>,
+) -> Result<()> {
+  let accepted_mint = &mut ctx.accounts.accepted_mint;
+  accepted_mint.disable()
+}
+
+#[derive(Accounts)]
+pub struct AddAcceptedMint<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + AcceptedMint::MAX_SIZE,
+    seeds = [
+      MINT_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump,
+  )]
+  pub accepted_mint: Account<'info, AcceptedMint>,
+  pub mint: Account<'info, Mint>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptedMintPriceSource<'info> {
+  #[account(mut)]
+  pub accepted_mint: Account<'info, AcceptedMint>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptedMintDepegPolicy<'info> {
+  #[account(mut)]
+  pub accepted_mint: Account<'info, AcceptedMint>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptedMintEnabled<'info> {
+  #[account(mut)]
+  pub accepted_mint: Account<'info, AcceptedMint>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptedMintDisabled<'info> {
+  #[account(mut)]
+  pub accepted_mint: Account<'info, AcceptedMint>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
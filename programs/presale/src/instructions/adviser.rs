@@ -1,11 +1,20 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer as SplTransfer};
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer as SplTransfer};
 use solana_program::sysvar::instructions::ID as IX_ID;
-use crate::config::{ USDC, USDT, ADVISER_TAG };
+use crate::config::{
+  ADVISER_TAG, REWARD_TAG, VAULT_TAG, CONFIG_TAG, ESCROW_TAG, BLOCKLIST_TAG,
+  ROLE_ADVISER_MANAGER, PAUSE_ADVISER_CLAIMS,
+};
 
 use crate::events;
 use crate::errors;
 use crate::state::adviser::*;
+use crate::state::presale::Presale;
+use crate::state::escrow::Escrow;
+use crate::state::config::Config;
+use crate::migration::realloc_account;
 
 pub fn init_adviser(
   ctx: Context<InitAdviser>,
@@ -16,6 +25,51 @@ pub fn init_adviser(
   adviser.init(c_percent, t_percent)
 }
 
+/// Moves an adviser of the first release to the current layout, its USDT and USDC rewards
+/// are credited to the reward accounts of the given mints, whose balances stay in the adviser ATAs.
+/// Unlike the other migrations it takes the adviser manager role, as the caller picks the mints
+pub fn migrate_adviser(
+  ctx: Context<MigrateAdviser>,
+  adviser_code: String,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let system_program = &ctx.accounts.system_program;
+  let usdt_reward = &mut ctx.accounts.usdt_reward;
+  let usdc_reward = &mut ctx.accounts.usdc_reward;
+  let adviser_info = ctx.accounts.adviser.to_account_info();
+
+  if adviser_info.data_len() != 8 + LegacyAdviser::MAX_SIZE {
+    return err!(errors::Presale::AccountAlreadyMigrated);
+  }
+
+  let legacy = {
+    let data = adviser_info.try_borrow_data()?;
+    if data[..8] != Adviser::DISCRIMINATOR {
+      return err!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+    }
+    LegacyAdviser::deserialize(&mut &data[8..])?
+  };
+  usdt_reward.set_reward(legacy.usdt_reward)?;
+  usdc_reward.set_reward(legacy.usdc_reward)?;
+
+  realloc_account(&adviser_info, payer, system_program, 8 + Adviser::MAX_SIZE)?;
+
+  // The current layout is written over zeroes, as the old fields no longer line up
+  let mut data = adviser_info.try_borrow_mut_data()?;
+  data[8..].fill(0);
+  let mut adviser = Adviser::try_deserialize(&mut &data[..])?;
+  adviser.migrate(&legacy)?;
+  adviser.try_serialize(&mut &mut data[..])?;
+
+  emit!(events::AdviserMigrated {
+    code: adviser_code,
+    usdt_reward: legacy.usdt_reward,
+    usdc_reward: legacy.usdc_reward,
+  });
+
+  Ok(())
+}
+
 pub fn set_adviser_interest(
   ctx: Context<SetAdviserInterest>,
   c_percent: u64,
@@ -25,6 +79,14 @@ pub fn set_adviser_interest(
   adviser.set_interest(c_percent, t_percent)
 }
 
+pub fn set_adviser_buyer_discount(
+  ctx: Context<SetAdviserBuyerDiscount>,
+  buyer_discount: u64,
+) -> Result<()> {
+  let adviser = &mut ctx.accounts.adviser;
+  adviser.set_buyer_discount(buyer_discount)
+}
+
 pub fn enable_adviser(
   ctx: Context<SetAdviserEnabled>,
 ) -> Result<()> {
@@ -44,13 +106,24 @@ pub fn claim_sol(
   adviser_code: String,
 ) -> Result<()> {
   let payer = &mut ctx.accounts.payer;
+  let presale = &ctx.accounts.presale;
   let adviser = &mut ctx.accounts.adviser;
+  let escrow = &ctx.accounts.escrow;
   
   let sol_interest = adviser.get_sol_reward();
   if sol_interest > 0 {
     adviser.reset_sol_reward().unwrap();
 
-    adviser.sub_lamports(sol_interest).unwrap();
+    // Escrowed presales keep the adviser share in the escrow until it is claimed
+    if presale.is_escrow() {
+      let escrow = match escrow {
+        Some(escrow) => escrow,
+        None => return err!(errors::Presale::EscrowAccountMissing),
+      };
+      escrow.sub_lamports(sol_interest)?;
+    } else {
+      adviser.sub_lamports(sol_interest).unwrap();
+    }
     payer.add_lamports(sol_interest).unwrap();
 
     emit!(events::ClaimedSol {
@@ -62,73 +135,106 @@ pub fn claim_sol(
   Ok(())
 }
 
-pub fn claim_usdc(
-  ctx: Context<ClaimUsdc>,
+pub fn claim_reward(
+  ctx: Context<ClaimReward>,
   adviser_code: String,
 ) -> Result<()> {
+  let presale = &ctx.accounts.presale;
   let adviser = &mut ctx.accounts.adviser;
-  
+  let adviser_reward = &mut ctx.accounts.adviser_reward;
+  let mint = &ctx.accounts.mint;
+  let escrow = &ctx.accounts.escrow;
+  let escrow_ata = &ctx.accounts.escrow_ata;
+
   let adviser_ata = &ctx.accounts.adviser_ata;
   let adviser_pda_ata = &ctx.accounts.adviser_pda_ata;
   let program = &ctx.accounts.token_program;
 
-  let amount = adviser.get_usdc_reward();
+  let amount = adviser_reward.get_reward();
   if amount == 0 {
     return err!(errors::Presale::AdviserNoFunds);
   }
 
-  adviser.reset_usdc_reward().unwrap();
-
-  let bump = &[ctx.bumps.adviser];
-  let seeds: &[&[u8]] = &[ADVISER_TAG, b"_", adviser_code.as_ref(), bump];
-  let signer_seeds = &[&seeds[..]];
-
-  let cpi_accounts = SplTransfer {
-    from: adviser_pda_ata.to_account_info(),
-    to: adviser_ata.to_account_info(),
-    authority: adviser.to_account_info(),
-  };
-  let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
-  token::transfer(ctx, amount).unwrap();
+  adviser_reward.reset_reward().unwrap();
+
+  // Escrowed presales keep the adviser share in the escrow until it is claimed
+  if presale.is_escrow() {
+    let (escrow, escrow_ata) = match (escrow, escrow_ata) {
+      (Some(escrow), Some(escrow_ata)) => (escrow, escrow_ata),
+      _ => return err!(errors::Presale::EscrowAccountMissing),
+    };
+
+    let mint_key = mint.key();
+    let bump = &[ctx.bumps.escrow.unwrap()];
+    let seeds: &[&[u8]] = &[ESCROW_TAG, b"_", mint_key.as_ref(), bump];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_accounts = SplTransfer {
+      from: escrow_ata.to_account_info(),
+      to: adviser_ata.to_account_info(),
+      authority: escrow.to_account_info(),
+    };
+    let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+    token::transfer(ctx, amount)?;
+  } else {
+    let bump = &[ctx.bumps.adviser];
+    let seeds: &[&[u8]] = &[ADVISER_TAG, b"_", adviser_code.as_ref(), bump];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_accounts = SplTransfer {
+      from: adviser_pda_ata.to_account_info(),
+      to: adviser_ata.to_account_info(),
+      authority: adviser.to_account_info(),
+    };
+    let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+    token::transfer(ctx, amount).unwrap();
+  }
 
-  emit!(events::ClaimedUsdc {
+  emit!(events::ClaimedReward {
     code: adviser_code,
+    mint: mint.key(),
     amount: amount,
   });
 
   Ok(())
 }
 
-pub fn claim_usdt(
-  ctx: Context<ClaimUsdt>,
+pub fn claim_token_reward(
+  ctx: Context<ClaimTokenReward>,
   adviser_code: String,
 ) -> Result<()> {
+  let presale = &ctx.accounts.presale;
   let adviser = &mut ctx.accounts.adviser;
-  
+
+  let vault = &ctx.accounts.vault;
   let adviser_ata = &ctx.accounts.adviser_ata;
-  let adviser_pda_ata = &ctx.accounts.adviser_pda_ata;
   let program = &ctx.accounts.token_program;
 
-  let amount = adviser.get_usdt_reward();
+  if !presale.is_claims_enabled() {
+    return err!(errors::Presale::ClaimsNotEnabled);
+  }
+
+  let amount = adviser.get_token_reward();
   if amount == 0 {
     return err!(errors::Presale::AdviserNoFunds);
   }
 
-  adviser.reset_usdt_reward().unwrap();
+  adviser.reset_token_reward()?;
 
-  let bump = &[ctx.bumps.adviser];
-  let seeds: &[&[u8]] = &[ADVISER_TAG, b"_", adviser_code.as_ref(), bump];
+  let bump = &[ctx.bumps.presale];
+  let seeds: &[&[u8]] = &[bump];
   let signer_seeds = &[&seeds[..]];
 
   let cpi_accounts = SplTransfer {
-    from: adviser_pda_ata.to_account_info(),
+    from: vault.to_account_info(),
     to: adviser_ata.to_account_info(),
-    authority: adviser.to_account_info(),
+    authority: presale.to_account_info(),
   };
   let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
-  token::transfer(ctx, amount).unwrap();
+  let amount = u64::try_from(amount).unwrap();
+  token::transfer(ctx, amount)?;
 
-  emit!(events::ClaimedUsdt {
+  emit!(events::ClaimedToken {
     code: adviser_code,
     amount: amount,
   });
@@ -151,6 +257,69 @@ pub struct InitAdviser<'info> {
     bump
   )]
   pub adviser: Account<'info, Adviser>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ADVISER_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(adviser_code: String)]
+pub struct MigrateAdviser<'info> {
+  #[account(
+    mut,
+    owner = crate::ID,
+    seeds = [
+      ADVISER_TAG,
+      b"_",
+      adviser_code.as_ref()
+    ],
+    bump,
+  )]
+  /// CHECK: deserialized by hand, as older accounts do not match the `Adviser` layout
+  pub adviser: UncheckedAccount<'info>,
+  pub usdt_mint: Account<'info, Mint>,
+  #[account(constraint = usdc_mint.key() != usdt_mint.key() @ errors::Presale::WrongMint)]
+  pub usdc_mint: Account<'info, Mint>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + AdviserReward::MAX_SIZE,
+    seeds = [
+      REWARD_TAG,
+      b"_",
+      adviser_code.as_ref(),
+      b"_",
+      usdt_mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub usdt_reward: Account<'info, AdviserReward>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + AdviserReward::MAX_SIZE,
+    seeds = [
+      REWARD_TAG,
+      b"_",
+      adviser_code.as_ref(),
+      b"_",
+      usdc_mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub usdc_reward: Account<'info, AdviserReward>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ADVISER_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
   pub system_program: Program<'info, System>,
@@ -158,24 +327,68 @@ pub struct InitAdviser<'info> {
 
 #[derive(Accounts)]
 pub struct SetAdviserInterest<'info> {
+  #[account(
+    mut,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
+  )]
+  pub adviser: Account<'info, Adviser>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ADVISER_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdviserBuyerDiscount<'info> {
+  #[account(
+    mut,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
+  )]
   pub adviser: Account<'info, Adviser>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ADVISER_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct SetAdviserEnabled<'info> {
-  #[account(mut)]
+  #[account(
+    mut,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
+  )]
   pub adviser: Account<'info, Adviser>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ADVISER_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct SetAdviserDisabled<'info> {
-  #[account(mut)]
+  #[account(
+    mut,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
+  )]
   pub adviser: Account<'info, Adviser>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ADVISER_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
@@ -183,6 +396,13 @@ pub struct SetAdviserDisabled<'info> {
 #[derive(Accounts)]
 #[instruction(adviser_code: String)]
 pub struct ClaimSol<'info> {
+  #[account(
+    seeds = [],
+    bump,
+    constraint = !presale.is_paused(PAUSE_ADVISER_CLAIMS) @ errors::Presale::AdviserClaimsPaused,
+    constraint = !presale.is_escrow_pending() @ errors::Presale::EscrowNotReleased,
+  )]
+  pub presale: Account<'info, Presale>,
   #[account(
     mut,
     seeds = [
@@ -190,21 +410,51 @@ pub struct ClaimSol<'info> {
       b"_",
       adviser_code.as_ref()
     ],
-    bump
+    bump,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
   )]
   pub adviser: Account<'info, Adviser>,
+  #[account(
+    mut,
+    seeds = [ESCROW_TAG],
+    bump,
+  )]
+  pub escrow: Option<Account<'info, Escrow>>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
 
   #[account(address = IX_ID)]
   /// CHECK: we need this for sign
   pub ix_sysvar: AccountInfo<'info>,
 
+  #[account(
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+    constraint = blocked_wallet.data_is_empty() @ errors::Presale::WalletBlocked,
+  )]
+  /// CHECK: only exists while the payer is blocked
+  pub blocked_wallet: UncheckedAccount<'info>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(adviser_code: String)]
-pub struct ClaimUsdc<'info> {
+pub struct ClaimReward<'info> {
+  #[account(
+    seeds = [],
+    bump,
+    constraint = !presale.is_paused(PAUSE_ADVISER_CLAIMS) @ errors::Presale::AdviserClaimsPaused,
+    constraint = !presale.is_escrow_pending() @ errors::Presale::EscrowNotReleased,
+  )]
+  pub presale: Account<'info, Presale>,
   #[account(
     mut,
     seeds = [
@@ -212,34 +462,87 @@ pub struct ClaimUsdc<'info> {
       b"_",
       adviser_code.as_ref()
     ],
-    bump
+    bump,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
   )]
   pub adviser: Account<'info, Adviser>,
+  pub mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    seeds = [
+      REWARD_TAG,
+      b"_",
+      adviser_code.as_ref(),
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub adviser_reward: Account<'info, AdviserReward>,
   #[account(
     mut,
-    constraint = adviser_ata.mint == USDC.parse::<Pubkey>().unwrap(),
+    constraint = adviser_ata.mint == mint.key() @ errors::Presale::WrongMint,
     constraint = adviser_ata.owner == payer.key(),
   )]
   pub adviser_ata: Account<'info, TokenAccount>,
   #[account(
     mut,
-    constraint = adviser_pda_ata.mint == USDC.parse::<Pubkey>().unwrap(),
+    constraint = adviser_pda_ata.mint == mint.key() @ errors::Presale::WrongMint,
     constraint = adviser_pda_ata.owner == adviser.key(),
   )]
   pub adviser_pda_ata: Account<'info, TokenAccount>,
+  #[account(
+    seeds = [
+      ESCROW_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump,
+  )]
+  pub escrow: Option<Account<'info, Escrow>>,
+  #[account(
+    mut,
+    associated_token::mint = mint,
+    associated_token::authority = escrow,
+  )]
+  pub escrow_ata: Option<Account<'info, TokenAccount>>,
   pub token_program: Program<'info, Token>,
 
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
+
   #[account(address = IX_ID)]
   /// CHECK: we need this for sign
   pub ix_sysvar: AccountInfo<'info>,
 
+  #[account(
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+    constraint = blocked_wallet.data_is_empty() @ errors::Presale::WalletBlocked,
+  )]
+  /// CHECK: only exists while the payer is blocked
+  pub blocked_wallet: UncheckedAccount<'info>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(adviser_code: String)]
-pub struct ClaimUsdt<'info> {
+pub struct ClaimTokenReward<'info> {
+  #[account(
+    seeds = [],
+    bump,
+    constraint = !presale.is_paused(PAUSE_ADVISER_CLAIMS) @ errors::Presale::AdviserClaimsPaused,
+    constraint = !presale.is_escrow_pending() @ errors::Presale::EscrowNotReleased,
+  )]
+  pub presale: Account<'info, Presale>,
   #[account(
     mut,
     seeds = [
@@ -247,27 +550,49 @@ pub struct ClaimUsdt<'info> {
       b"_",
       adviser_code.as_ref()
     ],
-    bump
+    bump,
+    constraint = adviser.to_account_info().data_len() == 8 + Adviser::MAX_SIZE @ errors::Presale::AdviserNotMigrated,
   )]
   pub adviser: Account<'info, Adviser>,
+  #[account(address = presale.get_token_mint())]
+  pub token_mint: Account<'info, Mint>,
   #[account(
     mut,
-    constraint = adviser_ata.mint == USDT.parse::<Pubkey>().unwrap(),
-    constraint = adviser_ata.owner == payer.key(),
+    seeds = [VAULT_TAG],
+    bump,
   )]
-  pub adviser_ata: Account<'info, TokenAccount>,
+  pub vault: Account<'info, TokenAccount>,
   #[account(
-    mut,
-    constraint = adviser_pda_ata.mint == USDT.parse::<Pubkey>().unwrap(),
-    constraint = adviser_pda_ata.owner == adviser.key(),
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = token_mint,
+    associated_token::authority = payer,
   )]
-  pub adviser_pda_ata: Account<'info, TokenAccount>,
+  pub adviser_ata: Account<'info, TokenAccount>,
   pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
 
   #[account(address = IX_ID)]
   /// CHECK: we need this for sign
   pub ix_sysvar: AccountInfo<'info>,
 
+  #[account(
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+    constraint = blocked_wallet.data_is_empty() @ errors::Presale::WalletBlocked,
+  )]
+  /// CHECK: only exists while the payer is blocked
+  pub blocked_wallet: UncheckedAccount<'info>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+
+use crate::events;
+use crate::errors;
+use crate::state::blocklist::*;
+use crate::state::config::Config;
+
+use crate::config::{ BLOCKLIST_TAG, CONFIG_TAG, ROLE_COMPLIANCE };
+
+pub fn block_wallet(
+  ctx: Context<BlockWallet>,
+  wallet: Pubkey,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let blocked_wallet = &mut ctx.accounts.blocked_wallet;
+  let clock = Clock::get()?;
+
+  blocked_wallet.init(wallet, payer.key(), clock.unix_timestamp)?;
+
+  emit!(events::WalletBlocked {
+    wallet: wallet,
+    admin: payer.key(),
+  });
+
+  Ok(())
+}
+
+pub fn unblock_wallet(
+  ctx: Context<UnblockWallet>,
+  wallet: Pubkey,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+
+  emit!(events::WalletUnblocked {
+    wallet: wallet,
+    admin: payer.key(),
+  });
+
+  Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct BlockWallet<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + BlockedWallet::MAX_SIZE,
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      wallet.as_ref()
+    ],
+    bump
+  )]
+  pub blocked_wallet: Account<'info, BlockedWallet>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_COMPLIANCE) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct UnblockWallet<'info> {
+  #[account(
+    mut,
+    close = payer,
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      wallet.as_ref()
+    ],
+    bump
+  )]
+  pub blocked_wallet: Account<'info, BlockedWallet>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_COMPLIANCE) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
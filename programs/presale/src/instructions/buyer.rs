@@ -0,0 +1,300 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+  associated_token::AssociatedToken,
+  token::{ self, Mint, Token, TokenAccount, Transfer as SplTransfer },
+};
+use solana_program::sysvar::instructions::ID as IX_ID;
+
+use crate::events;
+use crate::errors;
+use crate::state::presale::Presale;
+use crate::state::buyer::Buyer;
+use crate::state::iteration::Iteration;
+use crate::state::config::Config;
+use crate::merkle;
+use crate::migration::realloc_account;
+
+use crate::config::{ BUYER_TAG, VAULT_TAG, ITERATION_TAG, CONFIG_TAG, PAUSE_TOKEN_CLAIMS, ROLE_COMPLIANCE };
+
+pub fn claim_tokens(
+  ctx: Context<ClaimTokens>,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let presale = &ctx.accounts.presale;
+  let buyer = &mut ctx.accounts.buyer;
+
+  let vault = &ctx.accounts.vault;
+  let buyer_ata = &ctx.accounts.buyer_ata;
+  let program = &ctx.accounts.token_program;
+
+  if !presale.is_claims_enabled() {
+    return err!(errors::Presale::ClaimsNotEnabled);
+  }
+
+  let clock = Clock::get()?;
+  let vested = presale.get_vested(buyer.get_balance(), clock.unix_timestamp);
+  let amount = vested - buyer.get_claimed();
+  if amount == 0 {
+    return err!(errors::Presale::BuyerNoFunds);
+  }
+
+  buyer.increase_claimed(amount)?;
+
+  let bump = &[ctx.bumps.presale];
+  let seeds: &[&[u8]] = &[bump];
+  let signer_seeds = &[&seeds[..]];
+
+  let cpi_accounts = SplTransfer {
+    from: vault.to_account_info(),
+    to: buyer_ata.to_account_info(),
+    authority: presale.to_account_info(),
+  };
+  let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+  let amount = u64::try_from(amount).unwrap();
+  token::transfer(ctx, amount)?;
+
+  emit!(events::TokensClaimed {
+    buyer: payer.key(),
+    amount: amount,
+  });
+
+  Ok(())
+}
+
+pub fn submit_kyc_pass(
+  ctx: Context<SubmitKycPass>,
+  tier: u8,
+  expiry: i64,
+  issued_at: i64,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let buyer = &mut ctx.accounts.buyer;
+
+  buyer.set_kyc_pass(tier, expiry, issued_at)?;
+
+  emit!(events::KycPassVerified {
+    buyer: payer.key(),
+    tier: tier,
+    expiry: expiry,
+    issued_at: issued_at,
+  });
+
+  Ok(())
+}
+
+pub fn migrate_buyer(
+  ctx: Context<MigrateBuyer>,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let wallet = &ctx.accounts.wallet;
+  let system_program = &ctx.accounts.system_program;
+  let buyer_info = ctx.accounts.buyer.to_account_info();
+
+  realloc_account(&buyer_info, payer, system_program, 8 + Buyer::MAX_SIZE)?;
+
+  // The new fields read as zero after the realloc
+  let mut data = buyer_info.try_borrow_mut_data()?;
+  let mut buyer = Buyer::try_deserialize(&mut &data[..])?;
+  buyer.migrate()?;
+  buyer.try_serialize(&mut &mut data[..])?;
+
+  emit!(events::BuyerMigrated {
+    buyer: wallet.key(),
+    version: buyer.get_version(),
+    usd_pending: buyer.is_usd_pending(),
+  });
+
+  Ok(())
+}
+
+pub fn backfill_buyer_usd(
+  ctx: Context<BackfillBuyerUsd>,
+  wallet: Pubkey,
+  usd_amount: u128,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let buyer = &mut ctx.accounts.buyer;
+
+  buyer.backfill_usd_amount(usd_amount)?;
+
+  emit!(events::BuyerUsdBackfilled {
+    buyer: wallet,
+    usd_amount: usd_amount,
+    admin: payer.key(),
+  });
+
+  Ok(())
+}
+
+pub fn register_allocation(
+  ctx: Context<RegisterAllocation>,
+  iteration_id: i16,
+  allocation: u128,
+  proof: Vec<[u8; 32]>,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let iteration = &ctx.accounts.iteration;
+  let buyer = &mut ctx.accounts.buyer;
+
+  if !iteration.is_gated() {
+    return err!(errors::Presale::IterationNotGated);
+  }
+
+  let merkle_root = iteration.get_merkle_root();
+  let leaf = merkle::get_allocation_leaf(&payer.key(), allocation);
+  if !merkle::verify_proof(&proof, &merkle_root, leaf) {
+    return err!(errors::Presale::InvalidAllowlistProof);
+  }
+
+  buyer.set_allocation(iteration_id, merkle_root, allocation)?;
+
+  emit!(events::AllocationRegistered {
+    buyer: payer.key(),
+    iteration: iteration_id,
+    allocation: allocation,
+  });
+
+  Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimTokens<'info> {
+  #[account(
+    seeds = [],
+    bump,
+    constraint = !presale.is_paused(PAUSE_TOKEN_CLAIMS) @ errors::Presale::TokenClaimsPaused,
+    constraint = !presale.is_escrow_pending() @ errors::Presale::EscrowNotReleased,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump
+  )]
+  pub buyer: Account<'info, Buyer>,
+  #[account(address = presale.get_token_mint())]
+  pub token_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    seeds = [VAULT_TAG],
+    bump,
+  )]
+  pub vault: Account<'info, TokenAccount>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = token_mint,
+    associated_token::authority = payer,
+  )]
+  pub buyer_ata: Account<'info, TokenAccount>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(iteration_id: i16)]
+pub struct RegisterAllocation<'info> {
+  #[account(
+    seeds = [
+      ITERATION_TAG,
+      b"_",
+      iteration_id.to_le_bytes().as_ref()
+    ],
+    bump
+  )]
+  pub iteration: Account<'info, Iteration>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + Buyer::MAX_SIZE,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump
+  )]
+  pub buyer: Account<'info, Buyer>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitKycPass<'info> {
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + Buyer::MAX_SIZE,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump
+  )]
+  pub buyer: Account<'info, Buyer>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
+
+  #[account(address = IX_ID)]
+  /// CHECK: we need this for sign
+  pub ix_sysvar: AccountInfo<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateBuyer<'info> {
+  #[account(
+    mut,
+    owner = crate::ID,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      wallet.key().as_ref()
+    ],
+    bump,
+  )]
+  /// CHECK: deserialized after the realloc, as older accounts are too small for `Buyer`
+  pub buyer: UncheckedAccount<'info>,
+  /// CHECK: owner of the buyer account
+  pub wallet: UncheckedAccount<'info>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct BackfillBuyerUsd<'info> {
+  #[account(
+    mut,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      wallet.as_ref()
+    ],
+    bump
+  )]
+  pub buyer: Account<'info, Buyer>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_COMPLIANCE) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  pub payer: Signer<'info>,
+}
@@ -0,0 +1,336 @@
+use anchor_lang::prelude::*;
+
+use crate::events;
+use crate::errors;
+use crate::program::Presale as PresaleProgram;
+use crate::state::config::{ Config, PriceMode };
+
+use crate::config::{ CONFIG_TAG, ROLE_PRESALE_MANAGER, ROLE_TREASURY_MANAGER, KYC_TIERS };
+
+pub fn init_config(
+  ctx: Context<InitConfig>,
+  store: Pubkey,
+  signer: Pubkey,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.init(ctx.accounts.payer.key(), store, signer)
+}
+
+pub fn set_store(
+  ctx: Context<SetStore>,
+  store: Pubkey,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.set_store(store)
+}
+
+pub fn set_signer(
+  ctx: Context<SetSigner>,
+  signer: Pubkey,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.set_signer(signer)
+}
+
+pub fn set_oracle_policy(
+  ctx: Context<SetOraclePolicy>,
+  max_age: u64,
+  max_conf_ratio: u64,
+  price_mode: PriceMode,
+  max_divergence: u64,
+  allow_primary_only: bool,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.set_oracle_policy(max_age, max_conf_ratio, price_mode, max_divergence, allow_primary_only)
+}
+
+pub fn set_quote_band(
+  ctx: Context<SetQuoteBand>,
+  quote_band: u64,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.set_quote_band(quote_band)
+}
+
+pub fn set_kyc_signer(
+  ctx: Context<SetKycSigner>,
+  kyc_signer: Pubkey,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.set_kyc_signer(kyc_signer)
+}
+
+pub fn set_kyc_limits(
+  ctx: Context<SetKycLimits>,
+  kyc_limits: [u64; KYC_TIERS],
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.set_kyc_limits(kyc_limits)
+}
+
+pub fn add_admin(
+  ctx: Context<AddAdmin>,
+  admin: Pubkey,
+  roles: u8,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.add_admin(admin, roles)?;
+
+  emit!(events::AdminAdded {
+    admin: admin,
+    roles: roles,
+  });
+
+  Ok(())
+}
+
+pub fn remove_admin(
+  ctx: Context<RemoveAdmin>,
+  admin: Pubkey,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.remove_admin(admin)?;
+
+  emit!(events::AdminRemoved {
+    admin: admin,
+  });
+
+  Ok(())
+}
+
+pub fn grant_roles(
+  ctx: Context<SetAdminRoles>,
+  admin: Pubkey,
+  roles: u8,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.grant_roles(admin, roles)?;
+
+  emit!(events::RolesGranted {
+    admin: admin,
+    roles: roles,
+  });
+
+  Ok(())
+}
+
+pub fn revoke_roles(
+  ctx: Context<SetAdminRoles>,
+  admin: Pubkey,
+  roles: u8,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.revoke_roles(admin, roles)?;
+
+  emit!(events::RolesRevoked {
+    admin: admin,
+    roles: roles,
+  });
+
+  Ok(())
+}
+
+pub fn transfer_ownership(
+  ctx: Context<TransferOwnership>,
+  new_owner: Pubkey,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  config.propose_owner(new_owner)?;
+
+  emit!(events::OwnershipTransferStarted {
+    owner: config.get_owner(),
+    pending_owner: new_owner,
+  });
+
+  Ok(())
+}
+
+pub fn accept_ownership(
+  ctx: Context<AcceptOwnership>,
+) -> Result<()> {
+  let config = &mut ctx.accounts.config;
+  let previous_owner = config.get_owner();
+  config.accept_owner()?;
+
+  emit!(events::OwnershipTransferred {
+    previous_owner: previous_owner,
+    owner: config.get_owner(),
+  });
+
+  Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(store: Pubkey, signer: Pubkey)]
+pub struct InitConfig<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Config::MAX_SIZE,
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(
+    constraint = program.programdata_address()? == Some(program_data.key()),
+  )]
+  pub program: Program<'info, PresaleProgram>,
+  #[account(
+    constraint = program_data.upgrade_authority_address == Some(payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub program_data: Account<'info, ProgramData>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(store: Pubkey)]
+pub struct SetStore<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(signer: Pubkey)]
+pub struct SetSigner<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.is_owner(&payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(max_age: u64, max_conf_ratio: u64)]
+pub struct SetOraclePolicy<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quote_band: u64)]
+pub struct SetQuoteBand<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetKycSigner<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.is_owner(&payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetKycLimits<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(admin: Pubkey, roles: u8)]
+pub struct AddAdmin<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.is_owner(&payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(admin: Pubkey)]
+pub struct RemoveAdmin<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.is_owner(&payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(admin: Pubkey, roles: u8)]
+pub struct SetAdminRoles<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.is_owner(&payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_owner: Pubkey)]
+pub struct TransferOwnership<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.is_owner(&payer.key()) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwnership<'info> {
+  #[account(
+    mut,
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.get_pending_owner() == payer.key() @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
@@ -0,0 +1,384 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+  associated_token::AssociatedToken,
+  token::{ self, Mint, Token, TokenAccount, Transfer as SplTransfer },
+};
+
+use crate::events;
+use crate::errors;
+use crate::state::escrow::*;
+use crate::state::presale::Presale;
+use crate::state::buyer::Buyer;
+use crate::state::config::Config;
+
+use crate::config::{ ESCROW_TAG, CONTRIBUTION_TAG, BUYER_TAG, CONFIG_TAG, ROLE_TREASURY_MANAGER };
+
+pub fn init_sol_escrow(
+  ctx: Context<InitSolEscrow>,
+) -> Result<()> {
+  let escrow = &mut ctx.accounts.escrow;
+  escrow.init(Pubkey::default())
+}
+
+pub fn init_token_escrow(
+  ctx: Context<InitTokenEscrow>,
+) -> Result<()> {
+  let escrow = &mut ctx.accounts.escrow;
+  escrow.init(ctx.accounts.mint.key())
+}
+
+pub fn release_sol_escrow(
+  ctx: Context<ReleaseSolEscrow>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  let escrow = &mut ctx.accounts.escrow;
+  let store_info = &ctx.accounts.store_info;
+
+  presale.release_escrow()?;
+
+  let amount = escrow.get_deposited();
+  escrow.reset_deposited()?;
+
+  escrow.sub_lamports(amount)?;
+  store_info.add_lamports(amount)?;
+
+  emit!(events::EscrowReleased {
+    mint: escrow.get_mint(),
+    amount: amount,
+  });
+
+  Ok(())
+}
+
+pub fn release_token_escrow(
+  ctx: Context<ReleaseTokenEscrow>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  let escrow = &mut ctx.accounts.escrow;
+  let mint = &ctx.accounts.mint;
+
+  let escrow_ata = &ctx.accounts.escrow_ata;
+  let store_ata = &ctx.accounts.store_ata;
+  let program = &ctx.accounts.token_program;
+
+  presale.release_escrow()?;
+
+  let amount = escrow.get_deposited();
+  escrow.reset_deposited()?;
+
+  let mint_key = mint.key();
+  let bump = &[ctx.bumps.escrow];
+  let seeds: &[&[u8]] = &[ESCROW_TAG, b"_", mint_key.as_ref(), bump];
+  let signer_seeds = &[&seeds[..]];
+
+  let cpi_accounts = SplTransfer {
+    from: escrow_ata.to_account_info(),
+    to: store_ata.to_account_info(),
+    authority: escrow.to_account_info(),
+  };
+  let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+  token::transfer(ctx, amount)?;
+
+  emit!(events::EscrowReleased {
+    mint: mint_key,
+    amount: amount,
+  });
+
+  Ok(())
+}
+
+pub fn refund_sol(
+  ctx: Context<RefundSol>,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let presale = &mut ctx.accounts.presale;
+  let buyer = &mut ctx.accounts.buyer;
+  let escrow = &mut ctx.accounts.escrow;
+  let contribution = &ctx.accounts.contribution;
+
+  let amount = contribution.get_amount();
+  escrow.sub_lamports(amount)?;
+  payer.add_lamports(amount)?;
+
+  buyer.refund(Pubkey::default(), amount, contribution.get_usd_amount(), contribution.get_token_amount())?;
+  presale.refund(contribution.get_usd_amount(), contribution.get_released_amount())?;
+
+  emit!(events::Refunded {
+    buyer: payer.key(),
+    mint: escrow.get_mint(),
+    amount: amount,
+    token_amount: contribution.get_token_amount(),
+  });
+
+  Ok(())
+}
+
+/// Same as `refund_sol`, for a payment in an accepted mint
+pub fn refund_token(
+  ctx: Context<RefundToken>,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let presale = &mut ctx.accounts.presale;
+  let buyer = &mut ctx.accounts.buyer;
+  let escrow = &ctx.accounts.escrow;
+  let contribution = &ctx.accounts.contribution;
+  let mint = &ctx.accounts.mint;
+
+  let escrow_ata = &ctx.accounts.escrow_ata;
+  let buyer_ata = &ctx.accounts.buyer_ata;
+  let program = &ctx.accounts.token_program;
+
+  let amount = contribution.get_amount();
+
+  let mint_key = mint.key();
+  let bump = &[ctx.bumps.escrow];
+  let seeds: &[&[u8]] = &[ESCROW_TAG, b"_", mint_key.as_ref(), bump];
+  let signer_seeds = &[&seeds[..]];
+
+  let cpi_accounts = SplTransfer {
+    from: escrow_ata.to_account_info(),
+    to: buyer_ata.to_account_info(),
+    authority: escrow.to_account_info(),
+  };
+  let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+  token::transfer(ctx, amount)?;
+
+  buyer.refund(mint_key, amount, contribution.get_usd_amount(), contribution.get_token_amount())?;
+  presale.refund(contribution.get_usd_amount(), contribution.get_released_amount())?;
+
+  emit!(events::Refunded {
+    buyer: payer.key(),
+    mint: mint_key,
+    amount: amount,
+    token_amount: contribution.get_token_amount(),
+  });
+
+  Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitSolEscrow<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Escrow::MAX_SIZE,
+    seeds = [ESCROW_TAG],
+    bump,
+  )]
+  pub escrow: Account<'info, Escrow>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitTokenEscrow<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Escrow::MAX_SIZE,
+    seeds = [
+      ESCROW_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump,
+  )]
+  pub escrow: Account<'info, Escrow>,
+  #[account(
+    init,
+    payer = payer,
+    associated_token::mint = mint,
+    associated_token::authority = escrow,
+  )]
+  pub escrow_ata: Account<'info, TokenAccount>,
+  pub mint: Account<'info, Mint>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseSolEscrow<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    seeds = [ESCROW_TAG],
+    bump,
+  )]
+  pub escrow: Account<'info, Escrow>,
+  #[account(
+    mut,
+    address = config.get_store() @ errors::Presale::WrongStore,
+  )]
+  /// CHECK: store info
+  pub store_info: AccountInfo<'info>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseTokenEscrow<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    seeds = [
+      ESCROW_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump,
+  )]
+  pub escrow: Account<'info, Escrow>,
+  pub mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    associated_token::mint = mint,
+    associated_token::authority = escrow,
+  )]
+  pub escrow_ata: Account<'info, TokenAccount>,
+  #[account(
+    mut,
+    constraint = store_ata.mint == mint.key() @ errors::Presale::WrongMint,
+    constraint = store_ata.owner == config.get_store() @ errors::Presale::WrongStore,
+  )]
+  pub store_ata: Account<'info, TokenAccount>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RefundSol<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+    constraint = presale.is_refundable() @ errors::Presale::NotRefundable,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+  )]
+  pub buyer: Account<'info, Buyer>,
+  #[account(
+    mut,
+    seeds = [ESCROW_TAG],
+    bump,
+  )]
+  pub escrow: Account<'info, Escrow>,
+  #[account(
+    mut,
+    close = payer,
+    seeds = [
+      CONTRIBUTION_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+  )]
+  pub contribution: Account<'info, Contribution>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundToken<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+    constraint = presale.is_refundable() @ errors::Presale::NotRefundable,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    seeds = [
+      BUYER_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+  )]
+  pub buyer: Account<'info, Buyer>,
+  #[account(
+    seeds = [
+      ESCROW_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump,
+  )]
+  pub escrow: Account<'info, Escrow>,
+  pub mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    associated_token::mint = mint,
+    associated_token::authority = escrow,
+  )]
+  pub escrow_ata: Account<'info, TokenAccount>,
+  #[account(
+    mut,
+    close = payer,
+    seeds = [
+      CONTRIBUTION_TAG,
+      b"_",
+      payer.key().as_ref(),
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump,
+  )]
+  pub contribution: Account<'info, Contribution>,
+  #[account(
+    mut,
+    constraint = buyer_ata.mint == mint.key() @ errors::Presale::WrongMint,
+    constraint = buyer_ata.owner == payer.key(),
+  )]
+  pub buyer_ata: Account<'info, TokenAccount>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
+}
@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
+use crate::events;
+use crate::errors;
 use crate::state::iteration::Iteration;
 use crate::state::presale::Presale;
+use crate::state::config::Config;
+use crate::migration::realloc_account;
 
-use crate::config::ITERATION_TAG;
+use crate::config::{ ITERATION_TAG, CONFIG_TAG, ROLE_ITERATION_MANAGER };
 
 pub fn create_iteration(
   ctx: Context<CreateIteration>,
@@ -30,6 +34,40 @@ pub fn set_iteration_total(
   iteration.set_total(total)
 }
 
+pub fn set_iteration_schedule(
+  ctx: Context<SetIterationSchedule>,
+  start_ts: i64,
+  end_ts: i64,
+) -> Result<()> {
+  let iteration = &mut ctx.accounts.iteration;
+  iteration.set_schedule(start_ts, end_ts)
+}
+
+pub fn set_iteration_buy_limits(
+  ctx: Context<SetIterationBuyLimits>,
+  min_buy: u64,
+  max_buy: u64,
+) -> Result<()> {
+  let iteration = &mut ctx.accounts.iteration;
+  iteration.set_buy_limits(min_buy, max_buy)
+}
+
+pub fn set_iteration_allowlist(
+  ctx: Context<SetIterationAllowlist>,
+  merkle_root: [u8; 32],
+) -> Result<()> {
+  let iteration = &mut ctx.accounts.iteration;
+  iteration.set_merkle_root(merkle_root)
+}
+
+pub fn set_iteration_next(
+  ctx: Context<SetIterationNext>,
+  next_id: i16,
+) -> Result<()> {
+  let iteration = &mut ctx.accounts.iteration;
+  iteration.set_next_id(next_id)
+}
+
 pub fn open_iteration(
   ctx: Context<OpenIteration>,
 ) -> Result<()> {
@@ -43,8 +81,94 @@ pub fn open_iteration(
 pub fn close_iteration(
   ctx: Context<CloseIteration>,
 ) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  let iteration = &mut ctx.accounts.iteration;
+  iteration.close_iteration()?;
+
+  settle_leftover(presale, iteration, false)?;
+  Ok(())
+}
+
+pub fn advance_iteration(
+  ctx: Context<AdvanceIteration>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
   let iteration = &mut ctx.accounts.iteration;
-  iteration.close_iteration()
+  let next_iteration = &mut ctx.accounts.next_iteration;
+  let clock = Clock::get()?;
+
+  if !iteration.is_sold_out() && !iteration.has_ended(clock.unix_timestamp) {
+    return err!(errors::Presale::IterationNotFinished);
+  }
+
+  open_next_iteration(presale, iteration, next_iteration)
+}
+
+/// Closes `iteration` and opens `next` as the current iteration of the presale
+pub fn open_next_iteration(
+  presale: &mut Presale,
+  iteration: &mut Iteration,
+  next: &mut Iteration,
+) -> Result<()> {
+  if iteration.get_next_id() != next.get_id() {
+    return err!(errors::Presale::InvalidIterationChain);
+  }
+
+  iteration.close_iteration()?;
+  let rolled_over = settle_leftover(presale, iteration, true)?;
+
+  next.set_total(next.get_total() + rolled_over)?;
+  next.open()?;
+  presale.set_iteration(next.get_id())?;
+
+  emit!(events::IterationAdvanced {
+    previous: iteration.get_id(),
+    next: next.get_id(),
+    rolled_over: rolled_over,
+  });
+  Ok(())
+}
+
+fn settle_leftover(
+  presale: &mut Presale,
+  iteration: &Iteration,
+  can_rollover: bool,
+) -> Result<u128> {
+  let unsold = iteration.get_total() - iteration.get_sold();
+  let rolled_over = presale.settle_leftover(unsold, can_rollover)?;
+
+  emit!(events::LeftoverSettled {
+    iteration: iteration.get_id(),
+    policy: presale.get_leftover_policy(),
+    amount: unsold,
+  });
+  Ok(rolled_over)
+}
+
+pub fn migrate_iteration(
+  ctx: Context<MigrateIteration>,
+  id: i16,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let system_program = &ctx.accounts.system_program;
+  let iteration_info = ctx.accounts.iteration.to_account_info();
+
+  if iteration_info.data_len() != 8 + Iteration::LEGACY_SIZE {
+    return err!(errors::Presale::AccountAlreadyMigrated);
+  }
+
+  realloc_account(&iteration_info, payer, system_program, 8 + Iteration::MAX_SIZE)?;
+
+  let mut data = iteration_info.try_borrow_mut_data()?;
+  let mut iteration = Iteration::try_deserialize(&mut &data[..])?;
+  iteration.migrate()?;
+  iteration.try_serialize(&mut &mut data[..])?;
+
+  emit!(events::IterationMigrated {
+    iteration: id,
+  });
+
+  Ok(())
 }
 
 #[derive(Accounts)]
@@ -62,6 +186,12 @@ pub struct CreateIteration<'info> {
     bump,
   )]
   pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
   pub system_program: Program<'info, System>,
@@ -72,6 +202,12 @@ pub struct CreateIteration<'info> {
 pub struct SetIterationPrice<'info> {
   #[account(mut)]
   pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
@@ -81,6 +217,70 @@ pub struct SetIterationPrice<'info> {
 pub struct SetIterationTotal<'info> {
   #[account(mut)]
   pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_ts: i64, end_ts: i64)]
+pub struct SetIterationSchedule<'info> {
+  #[account(mut)]
+  pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetIterationBuyLimits<'info> {
+  #[account(mut)]
+  pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetIterationAllowlist<'info> {
+  #[account(mut)]
+  pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(next_id: i16)]
+pub struct SetIterationNext<'info> {
+  #[account(mut)]
+  pub iteration: Account<'info, Iteration>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
@@ -91,16 +291,66 @@ pub struct OpenIteration<'info> {
   pub iteration: Account<'info, Iteration>,
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct AdvanceIteration<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    constraint = iteration.get_id() == presale.get_current_iteration() @ errors::Presale::InactiveIteration,
+  )]
+  pub iteration: Account<'info, Iteration>,
+  #[account(mut)]
+  pub next_iteration: Account<'info, Iteration>,
+  pub payer: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct CloseIteration<'info> {
   #[account(mut)]
   pub iteration: Account<'info, Iteration>,
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_ITERATION_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
-}
\ No newline at end of file
+}
+
+#[derive(Accounts)]
+#[instruction(id: i16)]
+pub struct MigrateIteration<'info> {
+  #[account(
+    mut,
+    owner = crate::ID,
+    seeds = [
+      ITERATION_TAG,
+      b"_",
+      &id.to_le_bytes()
+    ],
+    bump,
+  )]
+  /// CHECK: deserialized after the realloc, as older accounts are too small for `Iteration`
+  pub iteration: UncheckedAccount<'info>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
@@ -1,6 +1,18 @@
 pub use presale::*;
 pub use iteration::*;
 pub use adviser::*;
+pub use buyer::*;
+pub use config::*;
+pub use accepted_mint::*;
+pub use price_attestation::*;
+pub use escrow::*;
+pub use blocklist::*;
 pub mod presale;
 pub mod iteration;
-pub mod adviser;
\ No newline at end of file
+pub mod adviser;
+pub mod buyer;
+pub mod config;
+pub mod accepted_mint;
+pub mod price_attestation;
+pub mod escrow;
+pub mod blocklist;
@@ -2,21 +2,30 @@ use anchor_lang::{
   prelude::*,
   solana_program::{ program::invoke, system_instruction::transfer },
 };
-use std::str::FromStr;
-use anchor_spl::token::{ self, Token, TokenAccount, Transfer as SplTransfer };
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{ self, Burn, Mint, Token, TokenAccount, Transfer as SplTransfer };
+use solana_program::sysvar::instructions::ID as IX_ID;
 use pyth_solana_receiver_sdk::price_update::{ get_feed_id_from_hex, PriceUpdateV2 };
 
 use crate::errors;
 use crate::events;
-use crate::state::presale::Presale;
+use crate::oracle::{ self, PriceFeed };
+use crate::instructions::iteration::open_next_iteration;
+use crate::state::presale::{ LeftoverPolicy, Presale };
 use crate::state::iteration::Iteration;
-use crate::state::adviser::Adviser;
+use crate::state::adviser::{ Adviser, AdviserReward };
+use crate::state::accepted_mint::{ AcceptedMint, DepegPolicy, PriceSource };
 use crate::state::buyer::Buyer;
+use crate::state::price_attestation::PriceAttestation;
+use crate::state::escrow::{ Contribution, Escrow };
+use crate::state::config::{ Config, OraclePolicy };
+use crate::migration::realloc_account;
 
 use crate::config::{
-  SOL_USD_PRICEFEED, STORE, USDC, USDT,
-  PRECISION, STABLE_PRECISION, ADVISER_TAG,
-  BUYER_TAG, FEED_MAX_AGE, FEED_ID,
+  PRECISION, ADVISER_TAG, REWARD_TAG, MINT_TAG,
+  BUYER_TAG, FEED_ID, VAULT_TAG,
+  CONFIG_TAG, ROLE_PRESALE_MANAGER, ROLE_TREASURY_MANAGER,
+  ROLE_PAUSER, PAUSE_BUYS, ESCROW_TAG, CONTRIBUTION_TAG, BLOCKLIST_TAG,
 };
 
 pub fn init_presale(
@@ -34,6 +43,23 @@ pub fn set_presale_min_buy(
   presale.set_min_buy(min)
 }
 
+pub fn set_presale_buyer_discount(
+  ctx: Context<SetPresaleBuyerDiscount>,
+  buyer_discount: u64,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_buyer_discount(buyer_discount)
+}
+
+pub fn set_presale_bonus(
+  ctx: Context<SetPresaleBonus>,
+  threshold: u64,
+  percent: u64,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_bonus(threshold, percent)
+}
+
 pub fn set_presale_interest(
   ctx: Context<SetPresaleInterest>,
   c_percent: u64,
@@ -57,74 +83,340 @@ pub fn close_presale(
   presale.close_presale()
 }
 
+pub fn pause_presale(
+  ctx: Context<PausePresale>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.pause()?;
+
+  emit!(events::PresalePaused {});
+  Ok(())
+}
+
+pub fn unpause_presale(
+  ctx: Context<UnpausePresale>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.unpause()?;
+
+  emit!(events::PresaleUnpaused {});
+  Ok(())
+}
+
+pub fn pause_subsystems(
+  ctx: Context<PauseSubsystems>,
+  flags: u8,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.pause_subsystems(flags)?;
+
+  emit!(events::SubsystemsPaused {
+    flags: flags,
+  });
+  Ok(())
+}
+
+pub fn unpause_subsystems(
+  ctx: Context<UnpauseSubsystems>,
+  flags: u8,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.unpause_subsystems(flags)?;
+
+  emit!(events::SubsystemsUnpaused {
+    flags: flags,
+  });
+  Ok(())
+}
+
+pub fn set_token_mint(
+  ctx: Context<SetTokenMint>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_token_mint(ctx.accounts.token_mint.key())
+}
+
+pub fn set_presale_vesting(
+  ctx: Context<SetPresaleVesting>,
+  tge_percent: u64,
+  cliff: i64,
+  duration: i64,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_vesting(tge_percent, cliff, duration)
+}
+
+pub fn set_presale_hard_cap(
+  ctx: Context<SetPresaleHardCap>,
+  hard_cap: u128,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_hard_cap(hard_cap)
+}
+
+pub fn set_presale_buy_limits(
+  ctx: Context<SetPresaleBuyLimits>,
+  max_wallet_buy: u64,
+  max_tx_buy: u64,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_buy_limits(max_wallet_buy, max_tx_buy)
+}
+
+pub fn set_presale_soft_cap(
+  ctx: Context<SetPresaleSoftCap>,
+  soft_cap: u128,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_soft_cap(soft_cap)
+}
+
+pub fn set_presale_leftover_policy(
+  ctx: Context<SetPresaleLeftoverPolicy>,
+  leftover_policy: LeftoverPolicy,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  presale.set_leftover_policy(leftover_policy)
+}
+
+/// Moves the supply reserved by the leftover policy from the vault to the store
+pub fn withdraw_reserve(
+  ctx: Context<WithdrawReserve>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  let vault = &ctx.accounts.vault;
+  let store_ata = &ctx.accounts.store_ata;
+  let program = &ctx.accounts.token_program;
+
+  let amount = presale.withdraw_reserve()?;
+
+  let bump = &[ctx.bumps.presale];
+  let seeds: &[&[u8]] = &[bump];
+  let signer_seeds = &[&seeds[..]];
+
+  let cpi_accounts = SplTransfer {
+    from: vault.to_account_info(),
+    to: store_ata.to_account_info(),
+    authority: presale.to_account_info(),
+  };
+  let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+  token::transfer(ctx, u64::try_from(amount).unwrap())?;
+
+  emit!(events::ReserveWithdrawn {
+    amount: amount,
+    store: store_ata.owner,
+  });
+
+  Ok(())
+}
+
+/// Burns the supply settled by the leftover policy from the vault
+pub fn burn_leftover(
+  ctx: Context<BurnLeftover>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  let vault = &ctx.accounts.vault;
+  let token_mint = &ctx.accounts.token_mint;
+  let program = &ctx.accounts.token_program;
+
+  let amount = presale.execute_burn()?;
+
+  let bump = &[ctx.bumps.presale];
+  let seeds: &[&[u8]] = &[bump];
+  let signer_seeds = &[&seeds[..]];
+
+  let cpi_accounts = Burn {
+    mint: token_mint.to_account_info(),
+    from: vault.to_account_info(),
+    authority: presale.to_account_info(),
+  };
+  let ctx = CpiContext::new_with_signer(program.to_account_info(), cpi_accounts, signer_seeds);
+  token::burn(ctx, u64::try_from(amount).unwrap())?;
+
+  emit!(events::LeftoverBurned {
+    amount: amount,
+  });
+
+  Ok(())
+}
+
+pub fn migrate_presale(
+  ctx: Context<MigratePresale>,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let system_program = &ctx.accounts.system_program;
+  let presale_info = ctx.accounts.presale.to_account_info();
+
+  if presale_info.data_len() != 8 + Presale::LEGACY_SIZE {
+    return err!(errors::Presale::AccountAlreadyMigrated);
+  }
+
+  realloc_account(&presale_info, payer, system_program, 8 + Presale::MAX_SIZE)?;
+
+  let mut data = presale_info.try_borrow_mut_data()?;
+  let mut presale = Presale::try_deserialize(&mut &data[..])?;
+  presale.migrate()?;
+  presale.try_serialize(&mut &mut data[..])?;
+
+  emit!(events::PresaleMigrated {
+    presale: presale_info.key(),
+  });
+
+  Ok(())
+}
+
+pub fn enable_claims(
+  ctx: Context<EnableClaims>,
+) -> Result<()> {
+  let presale = &mut ctx.accounts.presale;
+  let clock = Clock::get()?;
+  presale.enable_claims(clock.unix_timestamp)
+}
+
 pub fn buy_sol(
   ctx: Context<BuySol>,
   code: String,
   amount: u64,
 ) -> Result<()> {
   let to_account_infos = &mut ctx.accounts.to_account_infos();
+  let config = &ctx.accounts.config;
   let payer = &mut ctx.accounts.payer;
   let presale = &mut ctx.accounts.presale;
   let iteration = &mut ctx.accounts.iteration;
+  let next_iteration = &mut ctx.accounts.next_iteration;
   let buyer = &mut ctx.accounts.buyer;
   let adviser = &mut ctx.accounts.adviser;
   let price_update = &ctx.accounts.price_update;
+  let price_attestation = &ctx.accounts.price_attestation;
   let store_info = &mut ctx.accounts.store_info;
+  let escrow = &mut ctx.accounts.escrow;
+  let contribution = &mut ctx.accounts.contribution;
 
-  if !presale.is_open() {
-    return err!(errors::Presale::PresaleNotEnabled);
-  }
+  check_buy(presale, iteration)?;
 
-  if !iteration.is_open() {
-    return err!(errors::Presale::IterationClosed);
-  }
+  let feed_id = get_feed_id_from_hex(FEED_ID)?;
+  let secondary = price_attestation.as_ref().map(|source| source as &dyn PriceFeed);
+  let (price, expo) = oracle::get_price(price_update, secondary, &feed_id, &config.get_oracle_policy())?;
 
-  if presale.get_current_iteration() != iteration.get_id() {
-    return err!(errors::Presale::InactiveIteration);
+  process_sol_buy(to_account_infos, config, payer, presale, iteration, next_iteration, buyer, adviser, store_info, escrow, contribution, code, amount, price, expo)
+}
+
+pub fn buy_sol_with_quote(
+  ctx: Context<BuySolWithQuote>,
+  code: String,
+  amount: u64,
+  quote_price: u64,
+  nonce: u64,
+) -> Result<()> {
+  let to_account_infos = &mut ctx.accounts.to_account_infos();
+  let config = &ctx.accounts.config;
+  let payer = &mut ctx.accounts.payer;
+  let presale = &mut ctx.accounts.presale;
+  let iteration = &mut ctx.accounts.iteration;
+  let next_iteration = &mut ctx.accounts.next_iteration;
+  let buyer = &mut ctx.accounts.buyer;
+  let adviser = &mut ctx.accounts.adviser;
+  let price_update = &ctx.accounts.price_update;
+  let price_attestation = &ctx.accounts.price_attestation;
+  let store_info = &mut ctx.accounts.store_info;
+  let escrow = &mut ctx.accounts.escrow;
+  let contribution = &mut ctx.accounts.contribution;
+
+  check_buy(presale, iteration)?;
+
+  // The oracle still has to be healthy, the quote is only honored close to its price
+  let feed_id = get_feed_id_from_hex(FEED_ID)?;
+  let secondary = price_attestation.as_ref().map(|source| source as &dyn PriceFeed);
+  let (price, expo) = oracle::get_price(price_update, secondary, &feed_id, &config.get_oracle_policy())?;
+  let oracle_price = price * 10u128.pow(PRECISION) / 10u128.pow(expo);
+  let locked_price = u128::from(quote_price);
+  if locked_price.abs_diff(oracle_price) * 10u128.pow(PRECISION) > u128::from(config.get_quote_band()) * oracle_price {
+    return err!(errors::Presale::QuoteOutOfBand);
   }
 
-  if Pubkey::from_str(STORE) != Ok(store_info.key()){
-    return Err(error!(errors::Presale::WrongStore))
-  };
+  buyer.use_quote_nonce(nonce)?;
 
-  if Pubkey::from_str(SOL_USD_PRICEFEED) != Ok(price_update.key()){
-    return Err(error!(errors::Presale::WrongPriceFeedId))
-  };
-  
-  let (price, expo) = get_price_test(price_update).unwrap();
-  let usd_amount = u128::from(amount) * price / 10u128.pow(expo);
-  let token_amount = usd_amount * 10u128.pow(PRECISION) / u128::from(iteration.get_price());
+  emit!(events::QuoteFilled {
+    buyer: payer.key(),
+    price: quote_price,
+    nonce: nonce,
+  });
 
-  if presale.get_min_buy() > usd_amount {
-    return err!(errors::Presale::PresaleMinBuyNotReached);
-  }
+  process_sol_buy(to_account_infos, config, payer, presale, iteration, next_iteration, buyer, adviser, store_info, escrow, contribution, code, amount, locked_price, PRECISION)
+}
 
-  if iteration.get_sold() + token_amount > iteration.get_total() {
-    return err!(errors::Presale::IterationSupplyExceeded);
-  }
+#[allow(clippy::too_many_arguments)]
+fn process_sol_buy(
+  to_account_infos: &[AccountInfo],
+  config: &Config,
+  payer: &Signer,
+  presale: &mut Account<Presale>,
+  iteration: &mut Account<Iteration>,
+  next_iteration: &mut Option<Account<Iteration>>,
+  buyer: &mut Account<Buyer>,
+  adviser: &mut Account<Adviser>,
+  store_info: &AccountInfo,
+  escrow: &mut Option<Account<Escrow>>,
+  contribution: &mut Option<Account<Contribution>>,
+  code: String,
+  amount: u64,
+  price: u128,
+  expo: u32,
+) -> Result<()> {
+  let usd_amount = u128::from(amount) * price / 10u128.pow(expo);
+  check_buy_limits(config, presale, iteration, buyer, usd_amount)?;
+  let bonus_percent = presale.get_bonus_percent(buyer.get_usd_amount() + usd_amount);
+  let token_amounts = get_token_amounts(iteration, next_iteration, usd_amount, bonus_percent)?;
+  let token_amount = token_amounts.0 + token_amounts.1;
+  let bonus_amount = get_bonus_amount(bonus_percent, token_amount);
+  check_allocations(iteration, next_iteration, buyer, token_amounts)?;
   
-  let (adviser_sol_reward, adviser_token_reward) = get_interest(presale, &code, adviser, amount, token_amount).unwrap();
+  let (adviser_sol_reward, adviser_token_reward) = get_interest(presale, &code, adviser, amount, token_amount - bonus_amount).unwrap();
+  let (discount_amount, adviser_token_reward) = get_discount_amounts(presale, &code, adviser, token_amount - bonus_amount, adviser_token_reward);
+  // Adviser tokens are paid out of the same vault, so they count against the hard cap
+  let released_amount = token_amount + discount_amount + adviser_token_reward;
+  if presale.is_hard_cap_exceeded(released_amount) {
+    return err!(errors::Presale::HardCapExceeded);
+  }
   let mut to_amount = amount;
   if adviser_sol_reward > 0 {
     to_amount = to_amount - adviser_sol_reward;
   }
 
-  let instruction = &transfer(&payer.key(), &store_info.key(), to_amount);
-  invoke(instruction, to_account_infos).unwrap();
+  if presale.is_escrow() {
+    let (escrow, contribution) = match (escrow, contribution) {
+      (Some(escrow), Some(contribution)) => (escrow, contribution),
+      _ => return err!(errors::Presale::EscrowAccountMissing),
+    };
+
+    // The adviser share is escrowed too, so a refund returns exactly what was paid
+    let instruction = &transfer(&payer.key(), &escrow.key(), amount);
+    invoke(instruction, to_account_infos)?;
 
-  if adviser_sol_reward > 0 {
-    let instruction = &transfer(&payer.key(), &adviser.key(), adviser_sol_reward);
+    escrow.deposit(to_amount)?;
+    contribution.add(payer.key(), Pubkey::default(), amount, usd_amount, token_amount + discount_amount, released_amount)?;
+    presale.add_raised(usd_amount)?;
+  } else {
+    let instruction = &transfer(&payer.key(), &store_info.key(), to_amount);
     invoke(instruction, to_account_infos).unwrap();
+
+    if adviser_sol_reward > 0 {
+      let instruction = &transfer(&payer.key(), &adviser.key(), adviser_sol_reward);
+      invoke(instruction, to_account_infos).unwrap();
+    }
   }
 
   // Updating presale details
-  presale.add_sold(token_amount).unwrap();
+  presale.add_sold(released_amount).unwrap();
 
   // Updating iteration details
-  iteration.increase_sold(token_amount).unwrap();
+  let iteration_id = iteration.get_id();
+  let bought = [(iteration_id, token_amounts.0), (iteration.get_next_id(), token_amounts.1)];
+  increase_iterations_sold(presale, iteration, next_iteration, token_amounts)?;
 
   // Updating buyer details
-  buyer.increase_balance(token_amount).unwrap();
+  buyer.increase_balance(token_amount + discount_amount).unwrap();
+  buyer.record_purchase(Pubkey::default(), amount, usd_amount, &bought, Clock::get()?.unix_timestamp)?;
 
   // Updating adviser details
   if !code.is_empty() {
@@ -133,292 +425,774 @@ pub fn buy_sol(
   };
 
   emit!(events::BoughtWithSol {
-    iteration: iteration.get_id(),
+    iteration: iteration_id,
     buyer: payer.key(),
     adviser: code,
     amount: amount,
-    token_amount: token_amount,
+    token_amount: token_amount - bonus_amount,
+    bonus_amount: bonus_amount,
+    discount_amount: discount_amount,
+    iteration_token_amount: token_amounts.0,
+    next_iteration: if token_amounts.1 > 0 { bought[1].0 } else { -1 },
+    next_iteration_token_amount: token_amounts.1,
   });
   Ok(())
 }
 
-pub fn buy_usdc(
-  ctx: Context<BuyUsdc>,
+pub fn buy_with_token(
+  ctx: Context<BuyWithToken>,
   code: String,
   amount: u64,
 ) -> Result<()> {
+  let config = &ctx.accounts.config;
   let payer = &mut ctx.accounts.payer;
   let presale = &mut ctx.accounts.presale;
   let iteration = &mut ctx.accounts.iteration;
+  let next_iteration = &mut ctx.accounts.next_iteration;
   let buyer = &mut ctx.accounts.buyer;
   let adviser = &mut ctx.accounts.adviser;
+  let adviser_reward = &mut ctx.accounts.adviser_reward;
+  let accepted_mint = &ctx.accounts.accepted_mint;
+  let price_update = &ctx.accounts.price_update;
+  let price_attestation = &ctx.accounts.price_attestation;
 
   let buyer_ata = &ctx.accounts.buyer_ata;
   let store_ata = &ctx.accounts.store_ata;
   let adviser_pda_ata = &ctx.accounts.adviser_pda_ata;
   let token_program = &ctx.accounts.token_program;
+  let escrow = &mut ctx.accounts.escrow;
+  let escrow_ata = &ctx.accounts.escrow_ata;
+  let contribution = &mut ctx.accounts.contribution;
 
-  if !presale.is_open() {
-    return err!(errors::Presale::PresaleNotEnabled);
-  }
+  check_buy(presale, iteration)?;
 
-  if !iteration.is_open() {
-    return err!(errors::Presale::IterationClosed);
+  if !accepted_mint.is_enabled() {
+    return err!(errors::Presale::MintNotAccepted);
   }
 
-  if presale.get_current_iteration() != iteration.get_id() {
-    return err!(errors::Presale::InactiveIteration);
+  let usd_amount = get_token_usd_amount(accepted_mint, price_update, price_attestation, &config.get_oracle_policy(), amount)?;
+  check_buy_limits(config, presale, iteration, buyer, usd_amount)?;
+  let bonus_percent = presale.get_bonus_percent(buyer.get_usd_amount() + usd_amount);
+  let token_amounts = get_token_amounts(iteration, next_iteration, usd_amount, bonus_percent)?;
+  let token_amount = token_amounts.0 + token_amounts.1;
+  let bonus_amount = get_bonus_amount(bonus_percent, token_amount);
+  check_allocations(iteration, next_iteration, buyer, token_amounts)?;
+
+  let (adviser_cash_reward, adviser_token_reward) = get_interest(presale, &code, adviser, amount, token_amount - bonus_amount).unwrap();
+  let (discount_amount, adviser_token_reward) = get_discount_amounts(presale, &code, adviser, token_amount - bonus_amount, adviser_token_reward);
+  // Adviser tokens are paid out of the same vault, so they count against the hard cap
+  let released_amount = token_amount + discount_amount + adviser_token_reward;
+  if presale.is_hard_cap_exceeded(released_amount) {
+    return err!(errors::Presale::HardCapExceeded);
   }
-
-  let usd_amount = u128::from(amount) * 10u128.pow(STABLE_PRECISION);
-  let token_amount = usd_amount * 10u128.pow(PRECISION) / u128::from(iteration.get_price());
-
-  if presale.get_min_buy() > usd_amount {
-    return err!(errors::Presale::PresaleMinBuyNotReached);
+  let mut to_amount = amount;
+  if adviser_cash_reward > 0 {
+    to_amount = to_amount - adviser_cash_reward;
   }
 
-  if iteration.get_sold() + token_amount > iteration.get_total() {
-    return err!(errors::Presale::IterationSupplyExceeded);
-  }
+  if presale.is_escrow() {
+    let (escrow, escrow_ata, contribution) = match (escrow, escrow_ata, contribution) {
+      (Some(escrow), Some(escrow_ata), Some(contribution)) => (escrow, escrow_ata, contribution),
+      _ => return err!(errors::Presale::EscrowAccountMissing),
+    };
 
-  let (adviser_usdc_reward, adviser_token_reward) = get_interest(presale, &code, adviser, amount, token_amount).unwrap();
-  let mut to_amount = amount;
-  if adviser_usdc_reward > 0 {
-    to_amount = to_amount - adviser_usdc_reward;
-  }
+    if escrow_ata.key() != get_associated_token_address(&escrow.key(), &accepted_mint.get_mint()) {
+      return err!(errors::Presale::WrongMint);
+    }
 
-  let cpi_accounts = SplTransfer {
-    from: buyer_ata.to_account_info(),
-    to: store_ata.to_account_info(),
-    authority: payer.to_account_info(),
-  };
-  let cpi_program = token_program.to_account_info();
-  token::transfer(CpiContext::new(cpi_program, cpi_accounts), to_amount).unwrap();
-  
-  if adviser_usdc_reward > 0 {
+    // The adviser share is escrowed too, so a refund returns exactly what was paid
     let cpi_accounts = SplTransfer {
       from: buyer_ata.to_account_info(),
-      to: adviser_pda_ata.to_account_info(),
+      to: escrow_ata.to_account_info(),
       authority: payer.to_account_info(),
     };
     let cpi_program = token_program.to_account_info();
-    token::transfer(CpiContext::new(cpi_program, cpi_accounts), adviser_usdc_reward).unwrap();
+    token::transfer(CpiContext::new(cpi_program, cpi_accounts), amount)?;
+
+    escrow.deposit(to_amount)?;
+    contribution.add(payer.key(), accepted_mint.get_mint(), amount, usd_amount, token_amount + discount_amount, released_amount)?;
+    presale.add_raised(usd_amount)?;
+  } else {
+    let cpi_accounts = SplTransfer {
+      from: buyer_ata.to_account_info(),
+      to: store_ata.to_account_info(),
+      authority: payer.to_account_info(),
+    };
+    let cpi_program = token_program.to_account_info();
+    token::transfer(CpiContext::new(cpi_program, cpi_accounts), to_amount).unwrap();
+
+    if adviser_cash_reward > 0 {
+      let cpi_accounts = SplTransfer {
+        from: buyer_ata.to_account_info(),
+        to: adviser_pda_ata.to_account_info(),
+        authority: payer.to_account_info(),
+      };
+      let cpi_program = token_program.to_account_info();
+      token::transfer(CpiContext::new(cpi_program, cpi_accounts), adviser_cash_reward).unwrap();
+    }
   }
 
   // Updating presale details
-  presale.add_sold(token_amount).unwrap();
+  presale.add_sold(released_amount).unwrap();
 
   // Updating iteration details
-  iteration.increase_sold(token_amount).unwrap();
+  let iteration_id = iteration.get_id();
+  let bought = [(iteration_id, token_amounts.0), (iteration.get_next_id(), token_amounts.1)];
+  increase_iterations_sold(presale, iteration, next_iteration, token_amounts)?;
 
   // Updating buyer details
-  buyer.increase_balance(token_amount).unwrap();
+  buyer.increase_balance(token_amount + discount_amount).unwrap();
+  buyer.record_purchase(accepted_mint.get_mint(), amount, usd_amount, &bought, Clock::get()?.unix_timestamp)?;
 
   // Updating adviser details
   if !code.is_empty() {
-    adviser.set_usdc_reward(adviser_usdc_reward).unwrap();
+    adviser_reward.set_reward(adviser_cash_reward).unwrap();
     adviser.set_token_reward(adviser_token_reward).unwrap();
   };
 
-  emit!(events::BoughtWithUsdc {
-    iteration: iteration.get_id(),
+  emit!(events::BoughtWithToken {
+    iteration: iteration_id,
     buyer: payer.key(),
     adviser: code,
+    mint: accepted_mint.get_mint(),
     amount: amount,
-    token_amount: token_amount,
+    token_amount: token_amount - bonus_amount,
+    bonus_amount: bonus_amount,
+    discount_amount: discount_amount,
+    iteration_token_amount: token_amounts.0,
+    next_iteration: if token_amounts.1 > 0 { bought[1].0 } else { -1 },
+    next_iteration_token_amount: token_amounts.1,
   });
 
   Ok(())
 }
 
-pub fn buy_usdt(
-  ctx: Context<BuyUsdt>,
-  code: String,
-  amount: u64,
+fn check_buy(
+  presale: &Presale,
+  iteration: &Iteration,
 ) -> Result<()> {
-  let payer = &mut ctx.accounts.payer;
-  let presale = &mut ctx.accounts.presale;
-  let iteration = &mut ctx.accounts.iteration;
-  let buyer = &mut ctx.accounts.buyer;
-  let adviser = &mut ctx.accounts.adviser;
-
-  let buyer_ata = &ctx.accounts.buyer_ata;
-  let store_ata = &ctx.accounts.store_ata;
-  let adviser_pda_ata = &ctx.accounts.adviser_pda_ata;
-  let token_program = &ctx.accounts.token_program;
+  if presale.is_paused(PAUSE_BUYS) {
+    return err!(errors::Presale::BuysPaused);
+  }
 
   if !presale.is_open() {
     return err!(errors::Presale::PresaleNotEnabled);
   }
 
-  if !iteration.is_open() {
+  // An iteration past its end counts as closed without anyone closing it
+  let clock = Clock::get()?;
+  if !iteration.is_open() || iteration.has_ended(clock.unix_timestamp) {
     return err!(errors::Presale::IterationClosed);
   }
 
+  if !iteration.has_started(clock.unix_timestamp) {
+    return err!(errors::Presale::IterationNotStarted);
+  }
+
   if presale.get_current_iteration() != iteration.get_id() {
     return err!(errors::Presale::InactiveIteration);
   }
 
-  let usd_amount = u128::from(amount) * 10u128.pow(STABLE_PRECISION);
-  let token_amount = usd_amount * 10u128.pow (PRECISION) / u128::from(iteration.get_price());
+  Ok(())
+}
 
-  if presale.get_min_buy() > usd_amount {
+/// Checks `usd_amount` against the per transaction limits, which the iteration may override, the wallet limit
+/// and the cumulative limit of the buyer KYC tier
+fn check_buy_limits(
+  config: &Config,
+  presale: &Presale,
+  iteration: &Iteration,
+  buyer: &Buyer,
+  usd_amount: u128,
+) -> Result<()> {
+  if iteration.get_min_buy() != 0 {
+    if iteration.get_min_buy() > usd_amount {
+      return err!(errors::Presale::IterationMinBuyNotReached);
+    }
+  } else if presale.get_min_buy() > usd_amount {
     return err!(errors::Presale::PresaleMinBuyNotReached);
   }
 
-  if iteration.get_sold() + token_amount > iteration.get_total() {
-    return err!(errors::Presale::IterationSupplyExceeded);
+  if iteration.get_max_buy() != 0 {
+    if iteration.get_max_buy() < usd_amount {
+      return err!(errors::Presale::IterationMaxBuyExceeded);
+    }
+  } else if presale.get_max_tx_buy() != 0 && presale.get_max_tx_buy() < usd_amount {
+    return err!(errors::Presale::PresaleMaxBuyExceeded);
   }
 
-  let (adviser_usdt_reward, adviser_token_reward) = get_interest(presale, &code, adviser, amount, token_amount).unwrap();
-  let mut to_amount = amount;
-  if adviser_usdt_reward > 0 {
-    to_amount = to_amount - adviser_usdt_reward;
+  let kyc_limit = config.get_kyc_limit(buyer.get_kyc_tier(Clock::get()?.unix_timestamp));
+
+  // Wallet limits cannot be enforced against a USD amount that misses older purchases
+  if buyer.is_usd_pending() && (presale.get_max_wallet_buy() != 0 || kyc_limit != 0) {
+    return err!(errors::Presale::BuyerUsdNotBackfilled);
   }
 
-  let cpi_accounts = SplTransfer {
-    from: buyer_ata.to_account_info(),
-    to: store_ata.to_account_info(),
-    authority: payer.to_account_info(),
+  if presale.get_max_wallet_buy() != 0 && presale.get_max_wallet_buy() < buyer.get_usd_amount() + usd_amount {
+    return err!(errors::Presale::WalletMaxBuyExceeded);
+  }
+
+  if kyc_limit != 0 && kyc_limit < buyer.get_usd_amount() + usd_amount {
+    return err!(errors::Presale::KycLimitExceeded);
+  }
+
+  Ok(())
+}
+
+/// Checks the tokens bought in each gated iteration against the allocation the buyer registered.
+/// Bonus tokens count too, as they are drawn from the same gated supply
+fn check_allocations(
+  iteration: &Iteration,
+  next_iteration: &Option<Account<Iteration>>,
+  buyer: &Buyer,
+  token_amounts: (u128, u128),
+) -> Result<()> {
+  check_allocation(iteration, buyer, token_amounts.0)?;
+  if let Some(next_iteration) = next_iteration {
+    if token_amounts.1 > 0 {
+      check_allocation(next_iteration, buyer, token_amounts.1)?;
+    }
+  }
+
+  Ok(())
+}
+
+fn check_allocation(
+  iteration: &Iteration,
+  buyer: &Buyer,
+  token_amount: u128,
+) -> Result<()> {
+  if !iteration.is_gated() {
+    return Ok(());
+  }
+
+  match buyer.get_remaining_allocation(iteration.get_id(), &iteration.get_merkle_root()) {
+    None => err!(errors::Presale::NotAllowlisted),
+    Some(remaining) if remaining < token_amount => err!(errors::Presale::AllocationExceeded),
+    Some(_) => Ok(()),
+  }
+}
+
+/// Splits `usd_amount` into the tokens bought in `iteration` and, once it sells out, in the next iteration of the chain.
+/// Amounts include the volume bonus at `bonus_percent`, which is drawn from the same supply
+fn get_token_amounts(
+  iteration: &Iteration,
+  next_iteration: &Option<Account<Iteration>>,
+  usd_amount: u128,
+  bonus_percent: u64,
+) -> Result<(u128, u128)> {
+  let price = get_bonus_price(iteration.get_price(), bonus_percent);
+  let token_amount = usd_amount * 10u128.pow(PRECISION) / price;
+  let available = iteration.get_total() - iteration.get_sold();
+  if token_amount <= available {
+    return Ok((token_amount, 0));
+  }
+
+  let next_iteration = match next_iteration {
+    Some(next_iteration) if next_iteration.get_id() == iteration.get_next_id() => next_iteration,
+    _ => return err!(errors::Presale::IterationSupplyExceeded),
   };
-  let cpi_program = token_program.to_account_info();
-  token::transfer(CpiContext::new(cpi_program, cpi_accounts), to_amount).unwrap();
-  
-  if adviser_usdt_reward > 0 {
-    let cpi_accounts = SplTransfer {
-      from: buyer_ata.to_account_info(),
-      to: adviser_pda_ata.to_account_info(),
-      authority: payer.to_account_info(),
-    };
-    let cpi_program = token_program.to_account_info();
-    token::transfer(CpiContext::new(cpi_program, cpi_accounts), adviser_usdt_reward).unwrap();
+
+  let clock = Clock::get()?;
+  if !next_iteration.has_started(clock.unix_timestamp) || next_iteration.has_ended(clock.unix_timestamp) {
+    return err!(errors::Presale::IterationSupplyExceeded);
   }
 
-  // Updating presale details
-  presale.add_sold(token_amount).unwrap();
+  // The rest of the USD amount is priced at the next iteration
+  let spent_usd_amount = available * price / 10u128.pow(PRECISION);
+  let next_price = get_bonus_price(next_iteration.get_price(), bonus_percent);
+  let next_token_amount = (usd_amount - spent_usd_amount) * 10u128.pow(PRECISION) / next_price;
+  if next_iteration.get_sold() + next_token_amount > next_iteration.get_total() {
+    return err!(errors::Presale::IterationSupplyExceeded);
+  }
 
-  // Updating iteration details
-  iteration.increase_sold(token_amount).unwrap();
+  Ok((available, next_token_amount))
+}
 
-  // Updating buyer details
-  buyer.increase_balance(token_amount).unwrap();
+/// Price per token once the bonus is included, so a bonus buy spends its USD on more tokens
+fn get_bonus_price(
+  price: u64,
+  bonus_percent: u64,
+) -> u128 {
+  u128::from(price) * 10u128.pow(PRECISION) / (10u128.pow(PRECISION) + u128::from(bonus_percent))
+}
 
-  // Updating adviser details
-  if !code.is_empty() {
-    adviser.set_usdt_reward(adviser_usdt_reward).unwrap();
-    adviser.set_token_reward(adviser_token_reward).unwrap();
+/// Share of `token_amount` credited as bonus at `bonus_percent`
+fn get_bonus_amount(
+  bonus_percent: u64,
+  token_amount: u128,
+) -> u128 {
+  let bonus_percent = u128::from(bonus_percent);
+  token_amount * bonus_percent / (10u128.pow(PRECISION) + bonus_percent)
+}
+
+/// Discount credited to buyers using an adviser code on top of `token_amount`, at the higher of the presale
+/// default and the adviser own, and what is left of the adviser token share `adviser_token_reward`.
+/// The discount is carved out of that share, so it never draws on the iteration supply
+fn get_discount_amounts(
+  presale: &Presale,
+  code: &str,
+  adviser: &Adviser,
+  token_amount: u128,
+  adviser_token_reward: u128,
+) -> (u128, u128) {
+  if code.is_empty() {
+    return (0, adviser_token_reward);
+  }
+
+  let discount = u64::max(presale.get_buyer_discount(), adviser.get_buyer_discount());
+  let discount_amount = u128::min(token_amount * u128::from(discount) / 10u128.pow(PRECISION), adviser_token_reward);
+  (discount_amount, adviser_token_reward - discount_amount)
+}
+
+/// Records the sold amounts and advances the presale to the next iteration once the current one sells out
+fn increase_iterations_sold(
+  presale: &mut Presale,
+  iteration: &mut Iteration,
+  next_iteration: &mut Option<Account<Iteration>>,
+  (token_amount, next_token_amount): (u128, u128),
+) -> Result<()> {
+  iteration.increase_sold(token_amount)?;
+
+  if !iteration.is_sold_out() {
+    return Ok(());
+  }
+
+  match next_iteration {
+    Some(next_iteration) if next_iteration.get_id() == iteration.get_next_id() => {
+      next_iteration.increase_sold(next_token_amount)?;
+      open_next_iteration(presale, iteration, next_iteration)
+    }
+    _ => Ok(()),
+  }
+}
+
+fn get_token_usd_amount(
+  accepted_mint: &AcceptedMint,
+  price_update: &Option<Account<PriceUpdateV2>>,
+  price_attestation: &Option<Account<PriceAttestation>>,
+  policy: &OraclePolicy,
+  amount: u64,
+) -> Result<u128> {
+  let decimals = 10u128.pow(u32::from(accepted_mint.get_decimals()));
+  let usd_amount = u128::from(amount) * 10u128.pow(PRECISION) / decimals;
+
+  match accepted_mint.get_price_source() {
+    PriceSource::Peg => {
+      // Pegged mints without a feed are always taken 1:1
+      if !accepted_mint.has_feed() {
+        return Ok(usd_amount);
+      }
+
+      let (price, expo) = get_accepted_mint_price(accepted_mint, price_update, price_attestation, policy)?;
+      let peg_price = price * 10u128.pow(PRECISION) / 10u128.pow(expo);
+      if peg_price >= u128::from(accepted_mint.get_depeg_threshold()) {
+        return Ok(usd_amount);
+      }
+
+      match accepted_mint.get_depeg_policy() {
+        DepegPolicy::Reprice => Ok(usd_amount * price / 10u128.pow(expo)),
+        DepegPolicy::Reject => err!(errors::Presale::MintDepegged),
+      }
+    }
+    PriceSource::Oracle => {
+      let (price, expo) = get_accepted_mint_price(accepted_mint, price_update, price_attestation, policy)?;
+      Ok(usd_amount * price / 10u128.pow(expo))
+    }
+  }
+}
+
+fn get_accepted_mint_price(
+  accepted_mint: &AcceptedMint,
+  price_update: &Option<Account<PriceUpdateV2>>,
+  price_attestation: &Option<Account<PriceAttestation>>,
+  policy: &OraclePolicy,
+) -> Result<(u128, u32)> {
+  let price_update = match price_update {
+    Some(price_update) => price_update,
+    None => return err!(errors::Presale::MissingPriceFeed),
   };
 
-  emit!(events::BoughtWithUsdt {
-    iteration: iteration.get_id(),
-    buyer: payer.key(),
-    adviser: code,
-    amount: amount,
-    token_amount: token_amount,
-  });
+  let secondary = price_attestation.as_ref().map(|source| source as &dyn PriceFeed);
+  oracle::get_price(price_update, secondary, &accepted_mint.get_feed_id(), policy)
+}
 
-  Ok(())
+fn get_interest(
+  presale: &mut Account<Presale>,
+  code: &str,
+  adviser: &mut Account<Adviser>,
+  amount: u64,
+  token_amount: u128,
+)
+  -> Result<(u64, u128)>
+{
+  if code.is_empty() {
+    return Ok((0, 0));
+  };
+
+  let (p_c_percent, p_t_percent) = presale.get_percents();
+  let (a_c_percent, a_t_percent) = adviser.get_percents();
+
+  let c_percent = u64::max(p_c_percent, a_c_percent);
+  let t_percent = u64::max(p_t_percent, a_t_percent);
+
+  let amount = amount * c_percent / 10u64.pow(PRECISION);
+  let reward_token_amount = token_amount * u128::from(t_percent) / 10u128.pow(PRECISION);
+
+  Ok((amount, reward_token_amount))
+}
+
+#[derive(Accounts)]
+pub struct InitPresale<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Presale::MAX_SIZE,
+    seeds = [],
+    bump,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(min: u64)]
+pub struct SetPresaleMinBuy<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(main_interest: u64, secondary_interest: u64)]
+pub struct SetPresaleInterest<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPresaleBuyerDiscount<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(threshold: u64, percent: u64)]
+pub struct SetPresaleBonus<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPresale<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePresale<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PausePresale<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PAUSER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
 }
 
-fn get_price(price_update: &Account<PriceUpdateV2>)
-  -> Result<(u128, u32)>
-{
-  let feed_id = &get_feed_id_from_hex(FEED_ID)?;
-  let current_price = price_update.get_price_no_older_than(
-      &Clock::get()?,
-      FEED_MAX_AGE,
-      feed_id,
-  ).unwrap();
-  let price = u64::try_from(current_price.price).unwrap();
-  let expo = u32::try_from(-current_price.exponent).unwrap();
-  Ok((u128::from(price), expo))
-}
-
-fn get_price_test(_price_update: &AccountInfo)
-  -> Result<(u128, u32)>
-{
-  Ok((144000000000, 9))
+#[derive(Accounts)]
+pub struct UnpausePresale<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PAUSER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
 }
 
-fn get_interest(
-  presale: &mut Account<Presale>,
-  code: &str,
-  adviser: &mut Account<Adviser>,
-  amount: u64,
-  token_amount: u128,
-)
-  -> Result<(u64, u128)>
-{
-  if code.is_empty() {
-    return Ok((0, 0));
-  };
-
-  let (p_c_percent, p_t_percent) = presale.get_percents();
-  let (a_c_percent, a_t_percent) = adviser.get_percents();
-
-  let c_percent = u64::max(p_c_percent, a_c_percent);
-  let t_percent = u64::max(p_t_percent, a_t_percent);
-
-  let amount = amount * c_percent / 10u64.pow(PRECISION);
-  let reward_token_amount = token_amount * u128::from(t_percent) / 10u128.pow(PRECISION);
+#[derive(Accounts)]
+#[instruction(flags: u8)]
+pub struct PauseSubsystems<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PAUSER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
 
-  Ok((amount, reward_token_amount))
+#[derive(Accounts)]
+#[instruction(flags: u8)]
+pub struct UnpauseSubsystems<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PAUSER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct InitPresale<'info> {
+pub struct SetTokenMint<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  pub token_mint: Account<'info, Mint>,
   #[account(
     init,
     payer = payer,
-    space = 8 + Presale::MAX_SIZE,
-    seeds = [],
+    seeds = [VAULT_TAG],
     bump,
+    token::mint = token_mint,
+    token::authority = presale,
   )]
-  pub presale: Account<'info, Presale>,
+  pub vault: Account<'info, TokenAccount>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
   pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(min: u64)]
-pub struct SetPresaleMinBuy<'info> {
+#[instruction(tge_percent: u64, cliff: i64, duration: i64)]
+pub struct SetPresaleVesting<'info> {
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(main_interest: u64, secondary_interest: u64)]
-pub struct SetPresaleInterest<'info> {
+#[instruction(hard_cap: u128)]
+pub struct SetPresaleHardCap<'info> {
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(threshold: u64, percent: u64)]
-pub struct SetPresaleBonus<'info> {
+pub struct SetPresaleBuyLimits<'info> {
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct OpenPresale<'info> {
+#[instruction(soft_cap: u128)]
+pub struct SetPresaleSoftCap<'info> {
+  #[account(mut)]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPresaleLeftoverPolicy<'info> {
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ClosePresale<'info> {
+pub struct MigratePresale<'info> {
+  #[account(
+    mut,
+    owner = crate::ID,
+    seeds = [],
+    bump,
+  )]
+  /// CHECK: deserialized after the realloc, as older accounts are too small for `Presale`
+  pub presale: UncheckedAccount<'info>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawReserve<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(address = presale.get_token_mint())]
+  pub token_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    seeds = [VAULT_TAG],
+    bump,
+  )]
+  pub vault: Account<'info, TokenAccount>,
+  #[account(
+    mut,
+    constraint = store_ata.mint == token_mint.key() @ errors::Presale::WrongMint,
+    constraint = store_ata.owner == config.get_store() @ errors::Presale::WrongStore,
+  )]
+  pub store_ata: Account<'info, TokenAccount>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct BurnLeftover<'info> {
+  #[account(
+    mut,
+    seeds = [],
+    bump,
+  )]
+  pub presale: Account<'info, Presale>,
+  #[account(
+    mut,
+    address = presale.get_token_mint(),
+  )]
+  pub token_mint: Account<'info, Mint>,
+  #[account(
+    mut,
+    seeds = [VAULT_TAG],
+    bump,
+  )]
+  pub vault: Account<'info, TokenAccount>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_TREASURY_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  pub payer: Signer<'info>,
+  pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EnableClaims<'info> {
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRESALE_MANAGER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub payer: Signer<'info>,
 }
@@ -426,12 +1200,30 @@ pub struct ClosePresale<'info> {
 #[derive(Accounts)]
 #[instruction(code: String, amount: u64)]
 pub struct BuySol<'info> {
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+    constraint = blocked_wallet.data_is_empty() @ errors::Presale::WalletBlocked,
+  )]
+  /// CHECK: only exists while the payer is blocked
+  pub blocked_wallet: UncheckedAccount<'info>,
   #[account(mut)]
   pub payer: Signer<'info>,
   #[account(mut)]
   pub iteration: Account<'info, Iteration>,
+  #[account(mut)]
+  pub next_iteration: Option<Account<'info, Iteration>>,
   #[account(
     init_if_needed,
     payer = payer,
@@ -453,26 +1245,68 @@ pub struct BuySol<'info> {
       b"_",
       code.as_ref()
     ],
-    bump
+    bump,
+    // Buys without a code go through the empty code account, any other code must be a registered adviser.
+    // Advisers of the first release are rejected by the space check of `init_if_needed` until migrated
+    constraint = code.is_empty() || adviser.is_enabled() @ errors::Presale::AdviserNotActive,
   )]
   pub adviser: Account<'info, Adviser>,
-  /// CHECK: price oracle
-  pub price_update: AccountInfo<'info>,
-  #[account(mut)]
-    /// CHECK: store info
+  pub price_update: Account<'info, PriceUpdateV2>,
+  pub price_attestation: Option<Account<'info, PriceAttestation>>,
+  #[account(
+    mut,
+    address = config.get_store() @ errors::Presale::WrongStore,
+  )]
+  /// CHECK: store info
   pub store_info: AccountInfo<'info>,
+  #[account(
+    mut,
+    seeds = [ESCROW_TAG],
+    bump,
+  )]
+  pub escrow: Option<Account<'info, Escrow>>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + Contribution::MAX_SIZE,
+    seeds = [
+      CONTRIBUTION_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump
+  )]
+  pub contribution: Option<Account<'info, Contribution>>,
   pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(code: String, amount: u64)]
-pub struct BuyUsdc<'info> {
+pub struct BuySolWithQuote<'info> {
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+    constraint = blocked_wallet.data_is_empty() @ errors::Presale::WalletBlocked,
+  )]
+  /// CHECK: only exists while the payer is blocked
+  pub blocked_wallet: UncheckedAccount<'info>,
   #[account(mut)]
   pub payer: Signer<'info>,
   #[account(mut)]
   pub iteration: Account<'info, Iteration>,
+  #[account(mut)]
+  pub next_iteration: Option<Account<'info, Iteration>>,
   #[account(
     init_if_needed,
     payer = payer,
@@ -494,40 +1328,73 @@ pub struct BuyUsdc<'info> {
       b"_",
       code.as_ref()
     ],
-    bump
+    bump,
+    // Buys without a code go through the empty code account, any other code must be a registered adviser.
+    // Advisers of the first release are rejected by the space check of `init_if_needed` until migrated
+    constraint = code.is_empty() || adviser.is_enabled() @ errors::Presale::AdviserNotActive,
   )]
   pub adviser: Account<'info, Adviser>,
+  pub price_update: Account<'info, PriceUpdateV2>,
+  pub price_attestation: Option<Account<'info, PriceAttestation>>,
   #[account(
     mut,
-    constraint = buyer_ata.mint == USDC.parse::<Pubkey>().unwrap(),
-    constraint = buyer_ata.owner == payer.key(),
+    address = config.get_store() @ errors::Presale::WrongStore,
   )]
-  pub buyer_ata: Account<'info, TokenAccount>,
+  /// CHECK: store info
+  pub store_info: AccountInfo<'info>,
   #[account(
     mut,
-    constraint = store_ata.mint == USDC.parse::<Pubkey>().unwrap(),
-    constraint = store_ata.owner == STORE.parse::<Pubkey>().unwrap(),
+    seeds = [ESCROW_TAG],
+    bump,
   )]
-  pub store_ata: Account<'info, TokenAccount>,
+  pub escrow: Option<Account<'info, Escrow>>,
   #[account(
-    mut,
-    constraint = adviser_pda_ata.mint == USDC.parse::<Pubkey>().unwrap(),
-    constraint = adviser_pda_ata.owner == adviser.key(),
+    init_if_needed,
+    payer = payer,
+    space = 8 + Contribution::MAX_SIZE,
+    seeds = [
+      CONTRIBUTION_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump
   )]
-  pub adviser_pda_ata: Account<'info, TokenAccount>,
-  pub token_program: Program<'info, Token>,
+  pub contribution: Option<Account<'info, Contribution>>,
+
+  #[account(address = IX_ID)]
+  /// CHECK: we need this for sign
+  pub ix_sysvar: AccountInfo<'info>,
+
   pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(code: String, amount: u64)]
-pub struct BuyUsdt<'info> {
+pub struct BuyWithToken<'info> {
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+  )]
+  pub config: Account<'info, Config>,
   #[account(mut)]
   pub presale: Account<'info, Presale>,
+  #[account(
+    seeds = [
+      BLOCKLIST_TAG,
+      b"_",
+      payer.key().as_ref()
+    ],
+    bump,
+    constraint = blocked_wallet.data_is_empty() @ errors::Presale::WalletBlocked,
+  )]
+  /// CHECK: only exists while the payer is blocked
+  pub blocked_wallet: UncheckedAccount<'info>,
   #[account(mut)]
   pub payer: Signer<'info>,
   #[account(mut)]
   pub iteration: Account<'info, Iteration>,
+  #[account(mut)]
+  pub next_iteration: Option<Account<'info, Iteration>>,
   #[account(
     init_if_needed,
     payer = payer,
@@ -549,27 +1416,82 @@ pub struct BuyUsdt<'info> {
       b"_",
       code.as_ref()
     ],
-    bump
+    bump,
+    // Buys without a code go through the empty code account, any other code must be a registered adviser.
+    // Advisers of the first release are rejected by the space check of `init_if_needed` until migrated
+    constraint = code.is_empty() || adviser.is_enabled() @ errors::Presale::AdviserNotActive,
   )]
   pub adviser: Account<'info, Adviser>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + AdviserReward::MAX_SIZE,
+    seeds = [
+      REWARD_TAG,
+      b"_",
+      code.as_ref(),
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub adviser_reward: Account<'info, AdviserReward>,
+  pub mint: Account<'info, Mint>,
+  #[account(
+    seeds = [
+      MINT_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub accepted_mint: Account<'info, AcceptedMint>,
+  pub price_update: Option<Account<'info, PriceUpdateV2>>,
+  pub price_attestation: Option<Account<'info, PriceAttestation>>,
   #[account(
     mut,
-    constraint = buyer_ata.mint == USDT.parse::<Pubkey>().unwrap(),
+    constraint = buyer_ata.mint == mint.key() @ errors::Presale::WrongMint,
     constraint = buyer_ata.owner == payer.key(),
   )]
   pub buyer_ata: Account<'info, TokenAccount>,
   #[account(
     mut,
-    constraint = store_ata.mint == USDT.parse::<Pubkey>().unwrap(),
-    constraint = store_ata.owner == STORE.parse::<Pubkey>().unwrap(),
+    constraint = store_ata.mint == mint.key() @ errors::Presale::WrongMint,
+    constraint = store_ata.owner == config.get_store() @ errors::Presale::WrongStore,
   )]
   pub store_ata: Account<'info, TokenAccount>,
   #[account(
     mut,
-    constraint = adviser_pda_ata.mint == USDT.parse::<Pubkey>().unwrap(),
+    constraint = adviser_pda_ata.mint == mint.key() @ errors::Presale::WrongMint,
     constraint = adviser_pda_ata.owner == adviser.key(),
   )]
   pub adviser_pda_ata: Account<'info, TokenAccount>,
+  #[account(
+    mut,
+    seeds = [
+      ESCROW_TAG,
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub escrow: Option<Account<'info, Escrow>>,
+  #[account(mut)]
+  pub escrow_ata: Option<Account<'info, TokenAccount>>,
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + Contribution::MAX_SIZE,
+    seeds = [
+      CONTRIBUTION_TAG,
+      b"_",
+      payer.key().as_ref(),
+      b"_",
+      mint.key().as_ref()
+    ],
+    bump
+  )]
+  pub contribution: Option<Account<'info, Contribution>>,
   pub token_program: Program<'info, Token>,
   pub system_program: Program<'info, System>,
 }
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::events;
+use crate::errors;
+use crate::state::price_attestation::*;
+use crate::state::config::Config;
+
+use crate::config::{ ATTESTATION_TAG, CONFIG_TAG, ROLE_PRICE_ATTESTER };
+
+pub fn post_price_attestation(
+  ctx: Context<PostPriceAttestation>,
+  feed_id: [u8; 32],
+  price: u64,
+  expo: u32,
+) -> Result<()> {
+  let payer = &ctx.accounts.payer;
+  let price_attestation = &mut ctx.accounts.price_attestation;
+  let clock = Clock::get()?;
+
+  price_attestation.set_price(feed_id, price, expo, clock.unix_timestamp, payer.key())?;
+
+  emit!(events::PriceAttested {
+    feed_id: feed_id,
+    price: price,
+    expo: expo,
+    publish_time: clock.unix_timestamp,
+    attester: payer.key(),
+  });
+  Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(feed_id: [u8; 32])]
+pub struct PostPriceAttestation<'info> {
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PriceAttestation::MAX_SIZE,
+    seeds = [
+      ATTESTATION_TAG,
+      b"_",
+      feed_id.as_ref()
+    ],
+    bump,
+  )]
+  pub price_attestation: Account<'info, PriceAttestation>,
+  #[account(
+    seeds = [CONFIG_TAG],
+    bump,
+    constraint = config.has_role(&payer.key(), ROLE_PRICE_ATTESTER) @ errors::Presale::UnauthorizedSigner,
+  )]
+  pub config: Account<'info, Config>,
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  pub system_program: Program<'info, System>,
+}
@@ -1,9 +1,12 @@
 use instructions::*;
 use anchor_lang::prelude::*;
-use signature::check_signature;
+use signature::{ check_kyc_pass, check_quote, check_signature };
 
 pub mod config;
 pub mod signature;
+pub mod oracle;
+pub mod merkle;
+pub mod migration;
 pub mod errors;
 pub mod events;
 pub mod state;
@@ -15,13 +18,107 @@ declare_id!("7QzR3zsNQwn27MbVwyCBavBX7xZnBczmHuaUh8ViNPLS");
 pub mod presale {
   use super::*;
 
+  pub fn init_config(
+    ctx: Context<InitConfig>,
+    store: Pubkey,
+    signer: Pubkey,
+  ) -> Result<()> {
+    instructions::config::init_config(ctx, store, signer)
+  }
+
+  pub fn set_store(
+    ctx: Context<SetStore>,
+    store: Pubkey,
+  ) -> Result<()> {
+    instructions::config::set_store(ctx, store)
+  }
+
+  pub fn set_signer(
+    ctx: Context<SetSigner>,
+    signer: Pubkey,
+  ) -> Result<()> {
+    instructions::config::set_signer(ctx, signer)
+  }
+
+  pub fn set_oracle_policy(
+    ctx: Context<SetOraclePolicy>,
+    max_age: u64,
+    max_conf_ratio: u64,
+    price_mode: state::config::PriceMode,
+    max_divergence: u64,
+    allow_primary_only: bool,
+  ) -> Result<()> {
+    instructions::config::set_oracle_policy(ctx, max_age, max_conf_ratio, price_mode, max_divergence, allow_primary_only)
+  }
+
+  pub fn set_quote_band(
+    ctx: Context<SetQuoteBand>,
+    quote_band: u64,
+  ) -> Result<()> {
+    instructions::config::set_quote_band(ctx, quote_band)
+  }
+
+  pub fn set_kyc_signer(
+    ctx: Context<SetKycSigner>,
+    kyc_signer: Pubkey,
+  ) -> Result<()> {
+    instructions::config::set_kyc_signer(ctx, kyc_signer)
+  }
+
+  pub fn set_kyc_limits(
+    ctx: Context<SetKycLimits>,
+    kyc_limits: [u64; config::KYC_TIERS],
+  ) -> Result<()> {
+    instructions::config::set_kyc_limits(ctx, kyc_limits)
+  }
+
+  pub fn add_admin(
+    ctx: Context<AddAdmin>,
+    admin: Pubkey,
+    roles: u8,
+  ) -> Result<()> {
+    instructions::config::add_admin(ctx, admin, roles)
+  }
+
+  pub fn remove_admin(
+    ctx: Context<RemoveAdmin>,
+    admin: Pubkey,
+  ) -> Result<()> {
+    instructions::config::remove_admin(ctx, admin)
+  }
+
+  pub fn grant_roles(
+    ctx: Context<SetAdminRoles>,
+    admin: Pubkey,
+    roles: u8,
+  ) -> Result<()> {
+    instructions::config::grant_roles(ctx, admin, roles)
+  }
+
+  pub fn revoke_roles(
+    ctx: Context<SetAdminRoles>,
+    admin: Pubkey,
+    roles: u8,
+  ) -> Result<()> {
+    instructions::config::revoke_roles(ctx, admin, roles)
+  }
+
+  pub fn transfer_ownership(
+    ctx: Context<TransferOwnership>,
+    new_owner: Pubkey,
+  ) -> Result<()> {
+    instructions::config::transfer_ownership(ctx, new_owner)
+  }
+
+  pub fn accept_ownership(
+    ctx: Context<AcceptOwnership>,
+  ) -> Result<()> {
+    instructions::config::accept_ownership(ctx)
+  }
+
   pub fn init(
     ctx: Context<InitPresale>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::presale::init_presale(ctx)
   }
 
@@ -29,45 +126,138 @@ pub mod presale {
     ctx: Context<SetPresaleMinBuy>,
     min: u64,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::presale::set_presale_min_buy(ctx, min)
   }
 
+  pub fn set_presale_bonus(
+    ctx: Context<SetPresaleBonus>,
+    threshold: u64,
+    percent: u64,
+  ) -> Result<()> {
+    instructions::presale::set_presale_bonus(ctx, threshold, percent)
+  }
+
+  pub fn set_presale_buyer_discount(
+    ctx: Context<SetPresaleBuyerDiscount>,
+    buyer_discount: u64,
+  ) -> Result<()> {
+    instructions::presale::set_presale_buyer_discount(ctx, buyer_discount)
+  }
+
   pub fn set_presale_adviser_interest(
     ctx: Context<SetPresaleInterest>,
     c_percent: u64,
     t_percent: u64,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::presale::set_presale_interest(ctx, c_percent, t_percent)
   }
 
   pub fn open_presale(
     ctx: Context<OpenPresale>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::presale::open_presale(ctx)
   }
 
   pub fn close_presale(
     ctx: Context<ClosePresale>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::presale::close_presale(ctx)
   }
 
+  pub fn pause_presale(
+    ctx: Context<PausePresale>,
+  ) -> Result<()> {
+    instructions::presale::pause_presale(ctx)
+  }
+
+  pub fn unpause_presale(
+    ctx: Context<UnpausePresale>,
+  ) -> Result<()> {
+    instructions::presale::unpause_presale(ctx)
+  }
+
+  pub fn pause_subsystems(
+    ctx: Context<PauseSubsystems>,
+    flags: u8,
+  ) -> Result<()> {
+    instructions::presale::pause_subsystems(ctx, flags)
+  }
+
+  pub fn unpause_subsystems(
+    ctx: Context<UnpauseSubsystems>,
+    flags: u8,
+  ) -> Result<()> {
+    instructions::presale::unpause_subsystems(ctx, flags)
+  }
+
+  pub fn set_token_mint(
+    ctx: Context<SetTokenMint>,
+  ) -> Result<()> {
+    instructions::presale::set_token_mint(ctx)
+  }
+
+  pub fn set_presale_vesting(
+    ctx: Context<SetPresaleVesting>,
+    tge_percent: u64,
+    cliff: i64,
+    duration: i64,
+  ) -> Result<()> {
+    instructions::presale::set_presale_vesting(ctx, tge_percent, cliff, duration)
+  }
+
+  pub fn set_presale_hard_cap(
+    ctx: Context<SetPresaleHardCap>,
+    hard_cap: u128,
+  ) -> Result<()> {
+    instructions::presale::set_presale_hard_cap(ctx, hard_cap)
+  }
+
+  pub fn set_presale_buy_limits(
+    ctx: Context<SetPresaleBuyLimits>,
+    max_wallet_buy: u64,
+    max_tx_buy: u64,
+  ) -> Result<()> {
+    instructions::presale::set_presale_buy_limits(ctx, max_wallet_buy, max_tx_buy)
+  }
+
+  pub fn set_presale_soft_cap(
+    ctx: Context<SetPresaleSoftCap>,
+    soft_cap: u128,
+  ) -> Result<()> {
+    instructions::presale::set_presale_soft_cap(ctx, soft_cap)
+  }
+
+  pub fn set_presale_leftover_policy(
+    ctx: Context<SetPresaleLeftoverPolicy>,
+    leftover_policy: state::presale::LeftoverPolicy,
+  ) -> Result<()> {
+    instructions::presale::set_presale_leftover_policy(ctx, leftover_policy)
+  }
+
+  pub fn migrate_presale(
+    ctx: Context<MigratePresale>,
+  ) -> Result<()> {
+    instructions::presale::migrate_presale(ctx)
+  }
+
+  pub fn withdraw_reserve(
+    ctx: Context<WithdrawReserve>,
+  ) -> Result<()> {
+    instructions::presale::withdraw_reserve(ctx)
+  }
+
+  pub fn burn_leftover(
+    ctx: Context<BurnLeftover>,
+  ) -> Result<()> {
+    instructions::presale::burn_leftover(ctx)
+  }
+
+  pub fn enable_claims(
+    ctx: Context<EnableClaims>,
+  ) -> Result<()> {
+    instructions::presale::enable_claims(ctx)
+  }
+
   pub fn buy_sol(
     ctx: Context<BuySol>,
     adviser_code: String,
@@ -76,20 +266,108 @@ pub mod presale {
     instructions::presale::buy_sol(ctx, adviser_code, amount)
   }
 
-  pub fn buy_usdc(
-    ctx: Context<BuyUsdc>,
+  #[allow(clippy::too_many_arguments)]
+  pub fn buy_sol_with_quote(
+    ctx: Context<BuySolWithQuote>,
     adviser_code: String,
     amount: u64,
+    price: u64,
+    expiry: i64,
+    nonce: u64,
+    sig: [u8; 64],
+    sig_index: u32
   ) -> Result<()> {
-    instructions::presale::buy_usdc(ctx, adviser_code, amount)
+    check_quote(&ctx.accounts.payer, price, expiry, nonce, sig, &ctx.accounts.ix_sysvar, sig_index, &ctx.accounts.config)?;
+    instructions::presale::buy_sol_with_quote(ctx, adviser_code, amount, price, nonce)
   }
 
-  pub fn buy_usdt(
-    ctx: Context<BuyUsdt>,
+  pub fn buy_with_token(
+    ctx: Context<BuyWithToken>,
     adviser_code: String,
     amount: u64,
   ) -> Result<()> {
-    instructions::presale::buy_usdt(ctx, adviser_code, amount)
+    instructions::presale::buy_with_token(ctx, adviser_code, amount)
+  }
+
+  pub fn post_price_attestation(
+    ctx: Context<PostPriceAttestation>,
+    feed_id: [u8; 32],
+    price: u64,
+    expo: u32,
+  ) -> Result<()> {
+    instructions::price_attestation::post_price_attestation(ctx, feed_id, price, expo)
+  }
+
+  pub fn init_sol_escrow(
+    ctx: Context<InitSolEscrow>,
+  ) -> Result<()> {
+    instructions::escrow::init_sol_escrow(ctx)
+  }
+
+  pub fn init_token_escrow(
+    ctx: Context<InitTokenEscrow>,
+  ) -> Result<()> {
+    instructions::escrow::init_token_escrow(ctx)
+  }
+
+  pub fn release_sol_escrow(
+    ctx: Context<ReleaseSolEscrow>,
+  ) -> Result<()> {
+    instructions::escrow::release_sol_escrow(ctx)
+  }
+
+  pub fn release_token_escrow(
+    ctx: Context<ReleaseTokenEscrow>,
+  ) -> Result<()> {
+    instructions::escrow::release_token_escrow(ctx)
+  }
+
+  pub fn refund_sol(
+    ctx: Context<RefundSol>,
+  ) -> Result<()> {
+    instructions::escrow::refund_sol(ctx)
+  }
+
+  pub fn refund_token(
+    ctx: Context<RefundToken>,
+  ) -> Result<()> {
+    instructions::escrow::refund_token(ctx)
+  }
+
+  pub fn add_accepted_mint(
+    ctx: Context<AddAcceptedMint>,
+    price_source: state::accepted_mint::PriceSource,
+    feed_id: [u8; 32],
+  ) -> Result<()> {
+    instructions::accepted_mint::add_accepted_mint(ctx, price_source, feed_id)
+  }
+
+  pub fn set_accepted_mint_price_source(
+    ctx: Context<SetAcceptedMintPriceSource>,
+    price_source: state::accepted_mint::PriceSource,
+    feed_id: [u8; 32],
+  ) -> Result<()> {
+    instructions::accepted_mint::set_accepted_mint_price_source(ctx, price_source, feed_id)
+  }
+
+  pub fn set_accepted_mint_depeg_policy(
+    ctx: Context<SetAcceptedMintDepegPolicy>,
+    depeg_threshold: u64,
+    depeg_policy: state::accepted_mint::DepegPolicy,
+  ) -> Result<()> {
+    instructions::accepted_mint::set_accepted_mint_depeg_policy(ctx, depeg_threshold, depeg_policy)
+  }
+
+  pub fn enable_accepted_mint(
+    ctx: Context<SetAcceptedMintEnabled>,
+  ) -> Result<()> {
+    instructions::accepted_mint::enable_accepted_mint(ctx)
+  }
+
+  pub fn disable_accepted_mint(
+    ctx: Context<SetAcceptedMintDisabled>,
+  ) -> Result<()> {
+    instructions::accepted_mint::disable_accepted_mint(ctx)
   }
 
   pub fn create_iteration(
@@ -98,10 +376,6 @@ pub mod presale {
     price: u64,
     total_supply: u128,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::iteration::create_iteration(ctx, id, price, total_supply)
   }
 
@@ -109,10 +383,6 @@ pub mod presale {
     ctx: Context<SetIterationPrice>,
     price: u64,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::iteration::set_iteration_price(ctx, price)
   }
 
@@ -120,78 +390,107 @@ pub mod presale {
     ctx: Context<SetIterationTotal>,
     total_supply: u128,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::iteration::set_iteration_total(ctx, total_supply)
   }
 
+  pub fn set_iteration_schedule(
+    ctx: Context<SetIterationSchedule>,
+    start_ts: i64,
+    end_ts: i64,
+  ) -> Result<()> {
+    instructions::iteration::set_iteration_schedule(ctx, start_ts, end_ts)
+  }
+
+  pub fn set_iteration_buy_limits(
+    ctx: Context<SetIterationBuyLimits>,
+    min_buy: u64,
+    max_buy: u64,
+  ) -> Result<()> {
+    instructions::iteration::set_iteration_buy_limits(ctx, min_buy, max_buy)
+  }
+
+  pub fn set_iteration_allowlist(
+    ctx: Context<SetIterationAllowlist>,
+    merkle_root: [u8; 32],
+  ) -> Result<()> {
+    instructions::iteration::set_iteration_allowlist(ctx, merkle_root)
+  }
+
+  pub fn set_iteration_next(
+    ctx: Context<SetIterationNext>,
+    next_id: i16,
+  ) -> Result<()> {
+    instructions::iteration::set_iteration_next(ctx, next_id)
+  }
+
   pub fn open_iteration(
     ctx: Context<OpenIteration>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::iteration::open_iteration(ctx)
   }
 
+  pub fn advance_iteration(
+    ctx: Context<AdvanceIteration>,
+  ) -> Result<()> {
+    instructions::iteration::advance_iteration(ctx)
+  }
+
   pub fn close_iteration(
     ctx: Context<CloseIteration>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::iteration::close_iteration(ctx)
   }
 
+  pub fn migrate_iteration(
+    ctx: Context<MigrateIteration>,
+    id: i16,
+  ) -> Result<()> {
+    instructions::iteration::migrate_iteration(ctx, id)
+  }
+
   pub fn init_adviser(
     ctx: Context<InitAdviser>,
     _adviser_code: String,
     main_interest: u64,
     secondary_interest: u64,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::adviser::init_adviser(ctx, main_interest, secondary_interest)
   }
 
+  pub fn set_adviser_buyer_discount(
+    ctx: Context<SetAdviserBuyerDiscount>,
+    buyer_discount: u64,
+  ) -> Result<()> {
+    instructions::adviser::set_adviser_buyer_discount(ctx, buyer_discount)
+  }
+
   pub fn set_adviser_interest(
     ctx: Context<SetAdviserInterest>,
     main_interest: u64,
     secondary_interest: u64,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::adviser::set_adviser_interest(ctx, main_interest, secondary_interest)
   }
 
   pub fn enable_adviser(
     ctx: Context<SetAdviserEnabled>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::adviser::enable_adviser(ctx)
   }
 
   pub fn disable_adviser(
     ctx: Context<SetAdviserDisabled>,
   ) -> Result<()> {
-    if !config::only_owners(ctx.accounts.payer.key()) {
-      return err!(errors::Presale::UnauthorizedSigner);
-    }
-
     instructions::adviser::disable_adviser(ctx)
   }
 
+  pub fn migrate_adviser(
+    ctx: Context<MigrateAdviser>,
+    adviser_code: String,
+  ) -> Result<()> {
+    instructions::adviser::migrate_adviser(ctx, adviser_code)
+  }
+
   pub fn claim_sol(
     ctx: Context<ClaimSol>,
     adviser: String,
@@ -199,29 +498,84 @@ pub mod presale {
     sig: [u8; 64],
     sig_index: u32
   ) -> Result<()> {
-    check_signature(&adviser, &ctx.accounts.payer, sig, &ctx.accounts.ix_sysvar, deadline, sig_index).unwrap();
+    check_signature(&adviser, &ctx.accounts.payer, sig, &ctx.accounts.ix_sysvar, deadline, sig_index, &ctx.accounts.config).unwrap();
     instructions::adviser::claim_sol(ctx, adviser)
   }
 
-  pub fn claim_usdc(
-    ctx: Context<ClaimUsdc>,
+  pub fn claim_reward(
+    ctx: Context<ClaimReward>,
     adviser: String,
     deadline: u128,
     sig: [u8; 64],
     sig_index: u32
   ) -> Result<()> {
-    check_signature(&adviser, &ctx.accounts.payer, sig, &ctx.accounts.ix_sysvar, deadline, sig_index).unwrap();
-    instructions::adviser::claim_usdc(ctx, adviser)
+    check_signature(&adviser, &ctx.accounts.payer, sig, &ctx.accounts.ix_sysvar, deadline, sig_index, &ctx.accounts.config).unwrap();
+    instructions::adviser::claim_reward(ctx, adviser)
   }
 
-  pub fn claim_usdt(
-    ctx: Context<ClaimUsdt>,
+  pub fn claim_token_reward(
+    ctx: Context<ClaimTokenReward>,
     adviser: String,
     deadline: u128,
     sig: [u8; 64],
     sig_index: u32
   ) -> Result<()> {
-    check_signature(&adviser, &ctx.accounts.payer, sig, &ctx.accounts.ix_sysvar, deadline, sig_index).unwrap();
-    instructions::adviser::claim_usdt(ctx, adviser)
+    check_signature(&adviser, &ctx.accounts.payer, sig, &ctx.accounts.ix_sysvar, deadline, sig_index, &ctx.accounts.config).unwrap();
+    instructions::adviser::claim_token_reward(ctx, adviser)
+  }
+
+  pub fn claim_tokens(
+    ctx: Context<ClaimTokens>,
+  ) -> Result<()> {
+    instructions::buyer::claim_tokens(ctx)
+  }
+
+  pub fn register_allocation(
+    ctx: Context<RegisterAllocation>,
+    iteration_id: i16,
+    allocation: u128,
+    proof: Vec<[u8; 32]>,
+  ) -> Result<()> {
+    instructions::buyer::register_allocation(ctx, iteration_id, allocation, proof)
+  }
+
+  pub fn submit_kyc_pass(
+    ctx: Context<SubmitKycPass>,
+    tier: u8,
+    expiry: i64,
+    issued_at: i64,
+    sig: [u8; 64],
+    sig_index: u32
+  ) -> Result<()> {
+    check_kyc_pass(&ctx.accounts.payer, tier, expiry, issued_at, sig, &ctx.accounts.ix_sysvar, sig_index, &ctx.accounts.config)?;
+    instructions::buyer::submit_kyc_pass(ctx, tier, expiry, issued_at)
+  }
+
+  pub fn migrate_buyer(
+    ctx: Context<MigrateBuyer>,
+  ) -> Result<()> {
+    instructions::buyer::migrate_buyer(ctx)
+  }
+
+  pub fn backfill_buyer_usd(
+    ctx: Context<BackfillBuyerUsd>,
+    wallet: Pubkey,
+    usd_amount: u128,
+  ) -> Result<()> {
+    instructions::buyer::backfill_buyer_usd(ctx, wallet, usd_amount)
+  }
+
+  pub fn block_wallet(
+    ctx: Context<BlockWallet>,
+    wallet: Pubkey,
+  ) -> Result<()> {
+    instructions::blocklist::block_wallet(ctx, wallet)
+  }
+
+  pub fn unblock_wallet(
+    ctx: Context<UnblockWallet>,
+    wallet: Pubkey,
+  ) -> Result<()> {
+    instructions::blocklist::unblock_wallet(ctx, wallet)
   }
 }
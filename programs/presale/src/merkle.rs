@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+use solana_program::keccak::hashv;
+
+/// Leaf of an allowlist tree, committing a wallet to its token allocation
+pub fn get_allocation_leaf(
+  wallet: &Pubkey,
+  allocation: u128,
+) -> [u8; 32] {
+  hashv(&[wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+}
+
+/// Verifies `proof` for `leaf` against `root`, with each pair of nodes hashed in sorted order
+pub fn verify_proof(
+  proof: &[[u8; 32]],
+  root: &[u8; 32],
+  leaf: [u8; 32],
+) -> bool {
+  let computed = proof.iter().fold(leaf, |node, sibling| {
+    if node <= *sibling {
+      hashv(&[&node, sibling]).to_bytes()
+    } else {
+      hashv(&[sibling, &node]).to_bytes()
+    }
+  });
+
+  &computed == root
+}
@@ -0,0 +1,27 @@
+use anchor_lang::{
+  prelude::*,
+  solana_program::{ program::invoke, system_instruction::transfer },
+};
+
+/// Resizes `account` to `space` bytes, with `payer` topping up the rent. Anchor can only
+/// realloc typed accounts, which older layouts fail to deserialize into.
+///
+/// Accounts of the first release carry no version, so migrations recognise them by their size.
+/// Migrations that only move existing state into the current layout are left permissionless
+pub fn realloc_account<'info>(
+  account: &AccountInfo<'info>,
+  payer: &Signer<'info>,
+  system_program: &Program<'info, System>,
+  space: usize,
+) -> Result<()> {
+  let rent = Rent::get()?.minimum_balance(space);
+  let lamports = account.lamports();
+  if rent > lamports {
+    let instruction = &transfer(&payer.key(), &account.key(), rent - lamports);
+    invoke(instruction, &[payer.to_account_info(), account.clone(), system_program.to_account_info()])?;
+  }
+
+  account.realloc(space, true)?;
+
+  Ok(())
+}
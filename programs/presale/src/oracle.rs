@@ -0,0 +1,155 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::{ FeedId, PriceUpdateV2 };
+#[cfg(not(feature = "mock-oracle"))]
+use pyth_solana_receiver_sdk::price_update::VerificationLevel;
+
+use crate::errors;
+use crate::config::PRECISION;
+use crate::state::config::OraclePolicy;
+#[cfg(not(feature = "mock-oracle"))]
+use crate::state::config::PriceMode;
+use crate::state::price_attestation::PriceAttestation;
+
+/// A source of USD prices, returned as `(price, expo)` where the USD value is `price / 10^expo`
+pub trait PriceFeed {
+  fn get_feed_id(&self) -> FeedId;
+
+  fn get_publish_time(&self) -> i64;
+
+  fn get_price(&self, policy: &OraclePolicy) -> Result<(u128, u32)>;
+}
+
+impl<'info> PriceFeed for Account<'info, PriceUpdateV2> {
+  fn get_feed_id(&self) -> FeedId {
+    self.price_message.feed_id
+  }
+
+  #[cfg(not(feature = "mock-oracle"))]
+  fn get_publish_time(&self) -> i64 {
+    self.price_message.publish_time
+  }
+
+  /// The local fixture is never refreshed, so it is always treated as fresh
+  #[cfg(feature = "mock-oracle")]
+  fn get_publish_time(&self) -> i64 {
+    Clock::get().unwrap().unix_timestamp
+  }
+
+  #[cfg(not(feature = "mock-oracle"))]
+  fn get_price(&self, policy: &OraclePolicy) -> Result<(u128, u32)> {
+    if self.verification_level != VerificationLevel::Full {
+      return err!(errors::Presale::PriceNotVerified);
+    }
+
+    let message = &self.price_message;
+    let spot = (message.price, message.conf);
+    let ema = (message.ema_price, message.ema_conf);
+    // The more conservative price is the lower one, as it yields fewer tokens per unit paid
+    let (price, conf) = match policy.price_mode {
+      PriceMode::Spot => spot,
+      PriceMode::Ema => ema,
+      PriceMode::Worst => if ema.0 < spot.0 { ema } else { spot },
+    };
+
+    if price <= 0 || message.exponent > 0 {
+      return err!(errors::Presale::PriceIsDown);
+    }
+
+    let price = price as u128;
+    if u128::from(conf) * 10u128.pow(PRECISION) > u128::from(policy.max_conf_ratio) * price {
+      return err!(errors::Presale::PriceConfidenceTooWide);
+    }
+
+    Ok((price, message.exponent.unsigned_abs()))
+  }
+
+  /// Fixed $144 price for offline local tests
+  #[cfg(feature = "mock-oracle")]
+  fn get_price(&self, _policy: &OraclePolicy) -> Result<(u128, u32)> {
+    Ok((144000000000, 9))
+  }
+}
+
+impl<'info> PriceFeed for Account<'info, PriceAttestation> {
+  fn get_feed_id(&self) -> FeedId {
+    PriceAttestation::get_feed_id(self)
+  }
+
+  fn get_publish_time(&self) -> i64 {
+    PriceAttestation::get_publish_time(self)
+  }
+
+  fn get_price(&self, _policy: &OraclePolicy) -> Result<(u128, u32)> {
+    let (price, expo) = PriceAttestation::get_price(self);
+    Ok((u128::from(price), expo))
+  }
+}
+
+/// Returns the price of `feed_id` from `primary`, falling back to `secondary` when the
+/// primary is stale. When both are fresh they must agree within `policy.max_divergence`,
+/// a fresh primary alone is only accepted when `policy.allow_primary_only` is set
+pub fn get_price(
+  primary: &dyn PriceFeed,
+  secondary: Option<&dyn PriceFeed>,
+  feed_id: &FeedId,
+  policy: &OraclePolicy,
+) -> Result<(u128, u32)> {
+  check_feed_id(primary, feed_id)?;
+  if let Some(secondary) = secondary {
+    check_feed_id(secondary, feed_id)?;
+  }
+
+  let now = Clock::get()?.unix_timestamp;
+  let secondary = secondary.filter(|secondary| is_fresh(*secondary, now, policy));
+
+  if !is_fresh(primary, now, policy) {
+    return match secondary {
+      Some(secondary) => secondary.get_price(policy),
+      None => err!(errors::Presale::PriceTooOld),
+    };
+  }
+
+  let price = primary.get_price(policy)?;
+  match secondary {
+    Some(secondary) => check_divergence(price, secondary.get_price(policy)?, policy)?,
+    None if !policy.allow_primary_only => return err!(errors::Presale::PriceAttestationRequired),
+    None => {}
+  }
+
+  Ok(price)
+}
+
+fn check_feed_id(
+  source: &dyn PriceFeed,
+  feed_id: &FeedId,
+) -> Result<()> {
+  if &source.get_feed_id() != feed_id {
+    return err!(errors::Presale::WrongPriceFeedId);
+  }
+
+  Ok(())
+}
+
+fn is_fresh(
+  source: &dyn PriceFeed,
+  now: i64,
+  policy: &OraclePolicy,
+) -> bool {
+  let age = now.saturating_sub(source.get_publish_time());
+  age >= 0 && age as u64 <= policy.max_age
+}
+
+fn check_divergence(
+  (price, expo): (u128, u32),
+  (other_price, other_expo): (u128, u32),
+  policy: &OraclePolicy,
+) -> Result<()> {
+  let price = price * 10u128.pow(PRECISION) / 10u128.pow(expo);
+  let other_price = other_price * 10u128.pow(PRECISION) / 10u128.pow(other_expo);
+
+  if price.abs_diff(other_price) * 10u128.pow(PRECISION) > u128::from(policy.max_divergence) * price {
+    return err!(errors::Presale::PriceSourcesDiverge);
+  }
+
+  Ok(())
+}
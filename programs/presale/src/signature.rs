@@ -5,7 +5,9 @@ use solana_program::sysvar::instructions::load_instruction_at_checked;
 
 use std::convert::TryInto;
 
-use crate::{config, errors};
+use crate::config::{ KYC_DOMAIN, QUOTE_DOMAIN };
+use crate::errors;
+use crate::state::config::Config;
 
 pub fn check_signature(
   code: &String,
@@ -14,12 +16,10 @@ pub fn check_signature(
   ix_sysvar: &AccountInfo,
   deadline: u128,
   index: u32,
+  config: &Config,
 ) -> Result<()> {
-  let idx = usize::try_from(index).unwrap();
-  let ix: Instruction = load_instruction_at_checked(idx, &ix_sysvar)?;
   // Check that ix is what we expect to have been sent
   let message = format!("{}{}{}", code, payer.key(), deadline);
-  let pubkey = config::SIGNATURE_SIGNER.parse::<Pubkey>().unwrap();
 
   let clock: Clock = Clock::get()?;
   let now: u128 = clock.unix_timestamp.try_into().unwrap();
@@ -27,9 +27,84 @@ pub fn check_signature(
     return err!(errors::Presale::ExpiredSignature); 
   }
 
-  verify_ed25519(&ix, &pubkey.key().to_bytes(), &message.as_bytes(), &sig).unwrap();
+  check_signed_message(message.as_bytes(), sig, ix_sysvar, index, &config.get_signer())
+}
 
-  Ok(())
+#[allow(clippy::too_many_arguments)]
+pub fn check_quote(
+  payer: &Signer,
+  price: u64,
+  expiry: i64,
+  nonce: u64,
+  sig: [u8; 64],
+  ix_sysvar: &AccountInfo,
+  index: u32,
+  config: &Config,
+) -> Result<()> {
+  let message = [
+    QUOTE_DOMAIN,
+    &price.to_le_bytes(),
+    &expiry.to_le_bytes(),
+    payer.key().as_ref(),
+    &nonce.to_le_bytes(),
+  ].concat();
+
+  let clock: Clock = Clock::get()?;
+  if clock.unix_timestamp > expiry {
+    return err!(errors::Presale::QuoteExpired);
+  }
+
+  check_signed_message(&message, sig, ix_sysvar, index, &config.get_signer())
+}
+
+/// KYC passes are signed by the KYC signer rather than the config signer
+#[allow(clippy::too_many_arguments)]
+pub fn check_kyc_pass(
+  payer: &Signer,
+  tier: u8,
+  expiry: i64,
+  issued_at: i64,
+  sig: [u8; 64],
+  ix_sysvar: &AccountInfo,
+  index: u32,
+  config: &Config,
+) -> Result<()> {
+  let kyc_signer = config.get_kyc_signer();
+  if kyc_signer == Pubkey::default() {
+    return err!(errors::Presale::KycSignerNotSet);
+  }
+
+  let message = [
+    KYC_DOMAIN,
+    payer.key().as_ref(),
+    &[tier],
+    &expiry.to_le_bytes(),
+    &issued_at.to_le_bytes(),
+  ].concat();
+
+  let clock: Clock = Clock::get()?;
+  if clock.unix_timestamp > expiry {
+    return err!(errors::Presale::KycPassExpired);
+  }
+  if issued_at > clock.unix_timestamp {
+    return err!(errors::Presale::KycPassStale);
+  }
+
+  check_signed_message(&message, sig, ix_sysvar, index, &kyc_signer)
+}
+
+/// Verify that the instruction at `index` is an Ed25519Program signature of `message` by `signer`
+pub fn check_signed_message(
+  message: &[u8],
+  sig: [u8; 64],
+  ix_sysvar: &AccountInfo,
+  index: u32,
+  signer: &Pubkey,
+) -> Result<()> {
+  let idx = usize::try_from(index).unwrap();
+  let ix: Instruction = load_instruction_at_checked(idx, &ix_sysvar)?;
+
+  verify_ed25519(&ix, &signer.to_bytes(), message, &sig)
 }
 
 /// Verify Ed25519Program instruction fields
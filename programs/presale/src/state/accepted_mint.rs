@@ -0,0 +1,139 @@
+use anchor_lang::prelude::*;
+use crate::errors;
+use crate::config::PRECISION;
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub enum PriceSource {
+  Peg,
+  Oracle,
+}
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub enum DepegPolicy {
+  Reprice,
+  Reject,
+}
+
+#[account]
+pub struct AcceptedMint {
+  mint: Pubkey,
+  decimals: u8,
+  price_source: PriceSource,
+  feed_id: [u8; 32],
+  enabled: bool,
+
+  depeg_threshold: u64,
+  depeg_policy: DepegPolicy,
+}
+
+impl AcceptedMint {
+  pub const MAX_SIZE: usize = 32 + 1 + 1 + 32 + 1 + 8 + 1;
+
+  pub fn init(
+    &mut self,
+    mint: Pubkey,
+    decimals: u8,
+    price_source: PriceSource,
+    feed_id: [u8; 32],
+  ) -> Result<()> {
+    self.mint = mint;
+    self.decimals = decimals;
+    self.price_source = price_source;
+    self.feed_id = feed_id;
+    self.enabled = true;
+    self.depeg_threshold = 0;
+    self.depeg_policy = DepegPolicy::Reject;
+
+    Ok(())
+  }
+
+  pub fn set_price_source(
+    &mut self,
+    price_source: PriceSource,
+    feed_id: [u8; 32],
+  ) -> Result<()> {
+    self.price_source = price_source;
+    self.feed_id = feed_id;
+
+    Ok(())
+  }
+
+  pub fn set_depeg_policy(
+    &mut self,
+    depeg_threshold: u64,
+    depeg_policy: DepegPolicy,
+  ) -> Result<()> {
+    if depeg_threshold > 10u64.pow(PRECISION) {
+      return err!(errors::Presale::InvalidDepegThreshold);
+    }
+
+    self.depeg_threshold = depeg_threshold;
+    self.depeg_policy = depeg_policy;
+
+    Ok(())
+  }
+
+  pub fn enable(
+    &mut self,
+  ) -> Result<()> {
+    self.enabled = true;
+
+    Ok(())
+  }
+
+  pub fn disable(
+    &mut self,
+  ) -> Result<()> {
+    self.enabled = false;
+
+    Ok(())
+  }
+
+  pub fn get_mint(
+    &self,
+  ) -> Pubkey {
+    self.mint
+  }
+
+  pub fn get_decimals(
+    &self,
+  ) -> u8 {
+    self.decimals
+  }
+
+  pub fn get_price_source(
+    &self,
+  ) -> PriceSource {
+    self.price_source.clone()
+  }
+
+  pub fn get_feed_id(
+    &self,
+  ) -> [u8; 32] {
+    self.feed_id
+  }
+
+  pub fn has_feed(
+    &self,
+  ) -> bool {
+    self.feed_id != [0u8; 32]
+  }
+
+  pub fn get_depeg_threshold(
+    &self,
+  ) -> u64 {
+    self.depeg_threshold
+  }
+
+  pub fn get_depeg_policy(
+    &self,
+  ) -> DepegPolicy {
+    self.depeg_policy.clone()
+  }
+
+  pub fn is_enabled(
+    &self,
+  ) -> bool {
+    self.enabled
+  }
+}
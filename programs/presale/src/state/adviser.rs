@@ -1,4 +1,24 @@
 use anchor_lang::prelude::*;
+use crate::errors;
+use crate::config::PRECISION;
+
+/// Layout of accounts created by the first release, which kept the USDT and USDC rewards inline
+#[derive(AnchorDeserialize)]
+pub struct LegacyAdviser {
+  pub c_percent: u64,
+  pub t_percent: u64,
+
+  pub sol_reward: u64,
+  pub usdt_reward: u64,
+  pub usdc_reward: u64,
+  pub token_reward: u128,
+
+  pub enabled: bool,
+}
+
+impl LegacyAdviser {
+  pub const MAX_SIZE: usize = (5 * 8) + 16 + 1 + 3;
+}
 
 #[account]
 pub struct Adviser {
@@ -6,15 +26,15 @@ pub struct Adviser {
   t_percent: u64,
 
   sol_reward: u64,
-  usdt_reward: u64,
-  usdc_reward: u64,
   token_reward: u128,
 
   enabled: bool,
+
+  buyer_discount: u64,
 }
 
 impl Adviser {
-  pub const MAX_SIZE: usize = (5 * 8) + 16 + 1 + 3;
+  pub const MAX_SIZE: usize = (3 * 8) + 16 + 1 + 3 + 8;
 
   pub fn init(
     &mut self,
@@ -25,82 +45,86 @@ impl Adviser {
     self.t_percent = t_percent;
 
     self.sol_reward = 0;
-    self.usdt_reward = 0;
-    self.usdc_reward = 0;
     self.token_reward = 0;
 
     self.enabled = true;
+    self.buyer_discount = 0;
 
     Ok(())
   }
 
-  pub fn set_interest(
+  /// Carries over an account of the first release, its stablecoin rewards move to `AdviserReward` accounts
+  pub fn migrate(
     &mut self,
-    c_percent: u64,
-    t_percent: u64,
+    legacy: &LegacyAdviser,
   ) -> Result<()> {
-    self.c_percent = c_percent;
-    self.t_percent = t_percent;
+    self.c_percent = legacy.c_percent;
+    self.t_percent = legacy.t_percent;
 
-    Ok(())
-  }
+    self.sol_reward = legacy.sol_reward;
+    self.token_reward = legacy.token_reward;
 
-  pub fn set_sol_reward(
-    &mut self,
-    amount: u64,
-  ) -> Result<()> {
-    self.sol_reward += amount;
+    self.enabled = legacy.enabled;
+    self.buyer_discount = 0;
 
     Ok(())
   }
 
-  pub fn reset_sol_reward(
+  pub fn set_interest(
     &mut self,
+    c_percent: u64,
+    t_percent: u64,
   ) -> Result<()> {
-    self.sol_reward = 0;
+    self.c_percent = c_percent;
+    self.t_percent = t_percent;
 
     Ok(())
   }
 
-  pub fn set_usdt_reward(
+  /// Extra tokens given to buyers using this code, as a percent of their tokens, paid out of the adviser token share
+  pub fn set_buyer_discount(
     &mut self,
-    amount: u64,
+    buyer_discount: u64,
   ) -> Result<()> {
-    self.usdt_reward += amount;
+    if buyer_discount > 10u64.pow(PRECISION) {
+      return err!(errors::Presale::InvalidBuyerDiscount);
+    }
+
+    self.buyer_discount = buyer_discount;
 
     Ok(())
   }
 
-  pub fn reset_usdt_reward(
+  pub fn set_sol_reward(
     &mut self,
+    amount: u64,
   ) -> Result<()> {
-    self.usdt_reward = 0;
+    self.sol_reward += amount;
 
     Ok(())
   }
 
-  pub fn set_usdc_reward(
+  pub fn reset_sol_reward(
     &mut self,
-    amount: u64,
   ) -> Result<()> {
-    self.usdc_reward += amount;
+    self.sol_reward = 0;
 
     Ok(())
   }
 
-  pub fn reset_usdc_reward(
+  pub fn set_token_reward(
     &mut self,
+    amount: u128,
   ) -> Result<()> {
-    self.usdc_reward = 0;
+    self.token_reward += amount;
 
     Ok(())
   }
 
-  pub fn set_token_reward(
+  pub fn reset_token_reward(
     &mut self,
-    amount: u128,
   ) -> Result<()> {
-    self.token_reward += amount;
+    self.token_reward = 0;
 
     Ok(())
   }
@@ -111,22 +135,22 @@ impl Adviser {
     (self.c_percent, self.t_percent)
   }
 
-  pub fn get_sol_reward(
-    &mut self,
+  pub fn get_buyer_discount(
+    &self,
   ) -> u64 {
-    self.sol_reward
+    self.buyer_discount
   }
 
-  pub fn get_usdt_reward(
-    &mut self,
-  ) -> u64 {
-    self.usdt_reward
+  pub fn is_enabled(
+    &self,
+  ) -> bool {
+    self.enabled
   }
 
-  pub fn get_usdc_reward(
+  pub fn get_sol_reward(
     &mut self,
   ) -> u64 {
-    self.usdc_reward
+    self.sol_reward
   }
 
   pub fn get_token_reward(
@@ -151,3 +175,35 @@ impl Adviser {
     Ok(())
   }
 }
+
+#[account]
+pub struct AdviserReward {
+  amount: u64,
+}
+
+impl AdviserReward {
+  pub const MAX_SIZE: usize = 8;
+
+  pub fn set_reward(
+    &mut self,
+    amount: u64,
+  ) -> Result<()> {
+    self.amount += amount;
+
+    Ok(())
+  }
+
+  pub fn reset_reward(
+    &mut self,
+  ) -> Result<()> {
+    self.amount = 0;
+
+    Ok(())
+  }
+
+  pub fn get_reward(
+    &mut self,
+  ) -> u64 {
+    self.amount
+  }
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+/// Marks a sanctioned wallet, the account only exists while the wallet is blocked
+#[account]
+pub struct BlockedWallet {
+  wallet: Pubkey,
+  blocked_by: Pubkey,
+  blocked_at: i64,
+}
+
+impl BlockedWallet {
+  pub const MAX_SIZE: usize = (2 * 32) + 8;
+
+  pub fn init(
+    &mut self,
+    wallet: Pubkey,
+    blocked_by: Pubkey,
+    blocked_at: i64,
+  ) -> Result<()> {
+    self.wallet = wallet;
+    self.blocked_by = blocked_by;
+    self.blocked_at = blocked_at;
+
+    Ok(())
+  }
+
+  pub fn get_wallet(
+    &self,
+  ) -> Pubkey {
+    self.wallet
+  }
+}
@@ -1,17 +1,259 @@
 use anchor_lang::prelude::*;
+use crate::errors;
+use crate::config::{ BUYER_VERSION, MAX_BUYER_CURRENCIES, MAX_BUYER_ITERATIONS, KYC_TIERS };
+
+/// Amount paid in one currency, `Pubkey::default()` stands for SOL
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct CurrencyPaid {
+  pub mint: Pubkey,
+  pub amount: u64,
+  pub usd_amount: u128,
+}
+
+impl CurrencyPaid {
+  pub const MAX_SIZE: usize = 32 + 8 + 16;
+}
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct IterationBought {
+  pub iteration: i16,
+  pub amount: u128,
+}
+
+impl IterationBought {
+  pub const MAX_SIZE: usize = 2 + 16;
+}
+
+/// Allocation proven against the merkle root of a gated iteration
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct Allocation {
+  pub iteration: i16,
+  pub merkle_root: [u8; 32],
+  pub amount: u128,
+}
+
+impl Allocation {
+  pub const MAX_SIZE: usize = 2 + 32 + 16;
+}
 
 #[account]
 pub struct Buyer {
   balance: u128,
+  claimed: u128,
+  quote_nonce: u64,
+  // Past the end of version 0 accounts, so it reads as zero after the realloc
+  version: u8,
+
+  paid: Vec<CurrencyPaid>,
+  usd_amount: u128,
+  purchases: u32,
+  first_purchase_at: i64,
+  last_purchase_at: i64,
+  iterations: Vec<IterationBought>,
+
+  allocations: Vec<Allocation>,
+
+  kyc_tier: u8,
+  kyc_expiry: i64,
+  kyc_issued_at: i64,
+
+  // Set on migrated buyers whose balance predates the USD ledger
+  usd_pending: bool,
 }
 
 impl Buyer {
-  pub const MAX_SIZE: usize = 16 + 1;
+  /// Size of version 0 accounts, which only tracked the balance
+  pub const LEGACY_SIZE: usize = 16 + 1;
+  pub const MAX_SIZE: usize = (2 * 16) + 8 + 1
+    + (4 + MAX_BUYER_CURRENCIES * CurrencyPaid::MAX_SIZE)
+    + 16 + 4 + (2 * 8)
+    + (4 + MAX_BUYER_ITERATIONS * IterationBought::MAX_SIZE)
+    + (4 + MAX_BUYER_ITERATIONS * Allocation::MAX_SIZE)
+    + 1 + (2 * 8)
+    + 1;
 
   pub fn init(
     &mut self,
   ) -> Result<()> {
     self.balance = 0;
+    self.claimed = 0;
+    self.quote_nonce = 0;
+    self.version = BUYER_VERSION;
+    self.paid = Vec::new();
+    self.usd_amount = 0;
+    self.purchases = 0;
+    self.first_purchase_at = 0;
+    self.last_purchase_at = 0;
+    self.iterations = Vec::new();
+    self.allocations = Vec::new();
+    self.kyc_tier = 0;
+    self.kyc_expiry = 0;
+    self.kyc_issued_at = 0;
+    self.usd_pending = false;
+
+    Ok(())
+  }
+
+  /// Upgrades a reallocated account of an older version, the new fields start empty.
+  /// Purchases made before version 1 are only reflected in the balance, as the ledger
+  /// cannot be rebuilt on-chain, so their USD amount has to be backfilled by compliance
+  pub fn migrate(
+    &mut self,
+  ) -> Result<()> {
+    if self.version >= BUYER_VERSION {
+      return err!(errors::Presale::BuyerAlreadyMigrated);
+    }
+
+    if self.version == 0 && self.balance > 0 {
+      self.usd_pending = true;
+    }
+
+    self.version = BUYER_VERSION;
+
+    Ok(())
+  }
+
+  /// Accounts created through `init_if_needed` by a buy, a registration or a pass submission
+  /// are zeroed rather than initialized, so the first write stamps the current version
+  fn ensure_initialized(
+    &mut self,
+  ) {
+    self.version = BUYER_VERSION;
+  }
+
+  /// Registers the allocation of the buyer in a gated iteration, replacing any previous one
+  pub fn set_allocation(
+    &mut self,
+    iteration: i16,
+    merkle_root: [u8; 32],
+    amount: u128,
+  ) -> Result<()> {
+    self.ensure_initialized();
+
+    match self.allocations.iter_mut().find(|allocation| allocation.iteration == iteration) {
+      Some(allocation) => {
+        allocation.merkle_root = merkle_root;
+        allocation.amount = amount;
+      }
+      None => {
+        if self.allocations.len() >= MAX_BUYER_ITERATIONS {
+          return err!(errors::Presale::BuyerLedgerFull);
+        }
+        self.allocations.push(Allocation { iteration, merkle_root, amount });
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Replaces the KYC pass, only passes issued after the current one are accepted
+  pub fn set_kyc_pass(
+    &mut self,
+    tier: u8,
+    expiry: i64,
+    issued_at: i64,
+  ) -> Result<()> {
+    if usize::from(tier) >= KYC_TIERS {
+      return err!(errors::Presale::InvalidKycTier);
+    }
+    if issued_at <= self.kyc_issued_at {
+      return err!(errors::Presale::KycPassStale);
+    }
+
+    self.ensure_initialized();
+    self.kyc_tier = tier;
+    self.kyc_expiry = expiry;
+    self.kyc_issued_at = issued_at;
+
+    Ok(())
+  }
+
+  /// Records a purchase paid with `amount` of `mint`, worth `usd_amount`, split across
+  /// the iterations in `bought` as `(iteration, token_amount)`
+  pub fn record_purchase(
+    &mut self,
+    mint: Pubkey,
+    amount: u64,
+    usd_amount: u128,
+    bought: &[(i16, u128)],
+    timestamp: i64,
+  ) -> Result<()> {
+    self.ensure_initialized();
+
+    let paid_len = self.paid.len();
+    match self.paid.iter_mut().find(|paid| paid.mint == mint) {
+      Some(paid) => {
+        paid.amount += amount;
+        paid.usd_amount += usd_amount;
+      }
+      // The mint registry is unbounded, mints past the ledger size only count towards the USD amount
+      None if paid_len < MAX_BUYER_CURRENCIES => {
+        self.paid.push(CurrencyPaid { mint, amount, usd_amount });
+      }
+      None => {}
+    }
+
+    for &(iteration, amount) in bought.iter().filter(|(_, amount)| *amount > 0) {
+      match self.iterations.iter_mut().find(|bought| bought.iteration == iteration) {
+        Some(bought) => bought.amount += amount,
+        None => {
+          if self.iterations.len() >= MAX_BUYER_ITERATIONS {
+            return err!(errors::Presale::BuyerLedgerFull);
+          }
+          self.iterations.push(IterationBought { iteration, amount });
+        }
+      }
+    }
+
+    if self.purchases == 0 {
+      self.first_purchase_at = timestamp;
+    }
+    self.last_purchase_at = timestamp;
+    self.purchases += 1;
+    self.usd_amount += usd_amount;
+
+    Ok(())
+  }
+
+  /// Takes back a refunded contribution of `amount` of `mint`, worth `usd_amount` and credited
+  /// as `token_amount`. Per-iteration totals cannot be split by currency, so they are only
+  /// cleared once the whole balance is refunded
+  pub fn refund(
+    &mut self,
+    mint: Pubkey,
+    amount: u64,
+    usd_amount: u128,
+    token_amount: u128,
+  ) -> Result<()> {
+    if let Some(index) = self.paid.iter().position(|paid| paid.mint == mint) {
+      let paid = &mut self.paid[index];
+      paid.amount = paid.amount.saturating_sub(amount);
+      paid.usd_amount = paid.usd_amount.saturating_sub(usd_amount);
+      if paid.amount == 0 {
+        self.paid.remove(index);
+      }
+    }
+
+    self.balance = self.balance.saturating_sub(token_amount);
+    self.usd_amount = self.usd_amount.saturating_sub(usd_amount);
+    if self.balance == 0 {
+      self.iterations = Vec::new();
+    }
+
+    Ok(())
+  }
+
+  /// Sets the USD amount spent before the ledger existed, as computed off-chain
+  pub fn backfill_usd_amount(
+    &mut self,
+    usd_amount: u128,
+  ) -> Result<()> {
+    if !self.usd_pending {
+      return err!(errors::Presale::BuyerUsdAlreadyBackfilled);
+    }
+
+    self.usd_amount += usd_amount;
+    self.usd_pending = false;
 
     Ok(())
   }
@@ -25,9 +267,107 @@ impl Buyer {
     Ok(())
   }
 
+  pub fn increase_claimed(
+    &mut self,
+    amount: u128,
+  ) -> Result<()> {
+    self.claimed += amount;
+
+    Ok(())
+  }
+
+  /// Quotes are signed for the next nonce of the buyer, so each one can only be filled once
+  pub fn use_quote_nonce(
+    &mut self,
+    nonce: u64,
+  ) -> Result<()> {
+    if nonce != self.quote_nonce {
+      return err!(errors::Presale::QuoteNonceUsed);
+    }
+
+    self.quote_nonce += 1;
+
+    Ok(())
+  }
+
   pub fn get_balance(
     &mut self,
   ) -> u128 {
     self.balance
   }
+
+  pub fn get_claimed(
+    &mut self,
+  ) -> u128 {
+    self.claimed
+  }
+
+  pub fn get_quote_nonce(
+    &self,
+  ) -> u64 {
+    self.quote_nonce
+  }
+
+  pub fn get_version(
+    &self,
+  ) -> u8 {
+    self.version
+  }
+
+  pub fn get_paid(
+    &self,
+    mint: &Pubkey,
+  ) -> Option<&CurrencyPaid> {
+    self.paid.iter().find(|paid| &paid.mint == mint)
+  }
+
+  /// Tier of the last KYC pass, falling back to tier 0 once the pass expires
+  pub fn get_kyc_tier(
+    &self,
+    now: i64,
+  ) -> u8 {
+    if now > self.kyc_expiry {
+      return 0;
+    }
+
+    self.kyc_tier
+  }
+
+  pub fn get_usd_amount(
+    &self,
+  ) -> u128 {
+    self.usd_amount
+  }
+
+  pub fn is_usd_pending(
+    &self,
+  ) -> bool {
+    self.usd_pending
+  }
+
+  pub fn get_purchases(
+    &self,
+  ) -> u32 {
+    self.purchases
+  }
+
+  pub fn get_iteration_bought(
+    &self,
+    iteration: i16,
+  ) -> u128 {
+    self.iterations.iter()
+      .find(|bought| bought.iteration == iteration)
+      .map_or(0, |bought| bought.amount)
+  }
+
+  /// Allocation left in `iteration` under `merkle_root`, `None` when the buyer never registered against it
+  pub fn get_remaining_allocation(
+    &self,
+    iteration: i16,
+    merkle_root: &[u8; 32],
+  ) -> Option<u128> {
+    self.allocations.iter()
+      .find(|allocation| allocation.iteration == iteration && &allocation.merkle_root == merkle_root)
+      .map(|allocation| allocation.amount.saturating_sub(self.get_iteration_bought(iteration)))
+  }
 }
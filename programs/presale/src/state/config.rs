@@ -0,0 +1,319 @@
+use anchor_lang::prelude::*;
+use crate::errors;
+use crate::config::{ ALL_ROLES, FEED_MAX_AGE, FEED_MAX_CONF_RATIO, FEED_MAX_DIVERGENCE, QUOTE_MAX_BAND, KYC_TIERS };
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct Admin {
+  key: Pubkey,
+  roles: u8,
+}
+
+impl Admin {
+  pub const MAX_SIZE: usize = 32 + 1;
+}
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub enum PriceMode {
+  Spot,
+  Ema,
+  Worst,
+}
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct OraclePolicy {
+  pub max_age: u64,
+  pub max_conf_ratio: u64,
+  pub price_mode: PriceMode,
+  pub max_divergence: u64,
+  // Lets a fresh primary price through without a fresh attestation to check it against
+  pub allow_primary_only: bool,
+}
+
+impl OraclePolicy {
+  pub const MAX_SIZE: usize = (2 * 8) + 1 + 8 + 1;
+}
+
+#[account]
+pub struct Config {
+  owner: Pubkey,
+  pending_owner: Pubkey,
+  admins: Vec<Admin>,
+
+  store: Pubkey,
+  signer: Pubkey,
+
+  oracle_policy: OraclePolicy,
+  quote_band: u64,
+
+  kyc_signer: Pubkey,
+  kyc_limits: [u64; KYC_TIERS],
+}
+
+impl Config {
+  pub const MAX_ADMINS: usize = 10;
+  pub const MAX_SIZE: usize = (2 * 32) + 4 + (Admin::MAX_SIZE * Self::MAX_ADMINS) + (2 * 32) + OraclePolicy::MAX_SIZE + 8 + 32 + (8 * KYC_TIERS);
+
+  pub fn init(
+    &mut self,
+    owner: Pubkey,
+    store: Pubkey,
+    signer: Pubkey,
+  ) -> Result<()> {
+    self.owner = owner;
+    self.pending_owner = Pubkey::default();
+    self.admins = Vec::new();
+
+    self.store = store;
+    self.signer = signer;
+
+    self.oracle_policy = OraclePolicy {
+      max_age: FEED_MAX_AGE,
+      max_conf_ratio: FEED_MAX_CONF_RATIO,
+      price_mode: PriceMode::Spot,
+      max_divergence: FEED_MAX_DIVERGENCE,
+      allow_primary_only: false,
+    };
+    self.quote_band = QUOTE_MAX_BAND;
+
+    // KYC passes are rejected until a dedicated signer is set
+    self.kyc_signer = Pubkey::default();
+    self.kyc_limits = [0; KYC_TIERS];
+
+    Ok(())
+  }
+
+  pub fn set_store(
+    &mut self,
+    store: Pubkey,
+  ) -> Result<()> {
+    self.store = store;
+
+    Ok(())
+  }
+
+  pub fn set_signer(
+    &mut self,
+    signer: Pubkey,
+  ) -> Result<()> {
+    self.signer = signer;
+
+    Ok(())
+  }
+
+  pub fn set_oracle_policy(
+    &mut self,
+    max_age: u64,
+    max_conf_ratio: u64,
+    price_mode: PriceMode,
+    max_divergence: u64,
+    allow_primary_only: bool,
+  ) -> Result<()> {
+    if max_age == 0 || max_conf_ratio == 0 {
+      return err!(errors::Presale::InvalidOraclePolicy);
+    }
+
+    self.oracle_policy = OraclePolicy { max_age, max_conf_ratio, price_mode, max_divergence, allow_primary_only };
+
+    Ok(())
+  }
+
+  pub fn set_quote_band(
+    &mut self,
+    quote_band: u64,
+  ) -> Result<()> {
+    self.quote_band = quote_band;
+
+    Ok(())
+  }
+
+  pub fn set_kyc_signer(
+    &mut self,
+    kyc_signer: Pubkey,
+  ) -> Result<()> {
+    self.kyc_signer = kyc_signer;
+
+    Ok(())
+  }
+
+  /// Cumulative USD a buyer may spend at each KYC tier, zero leaves a tier unlimited
+  pub fn set_kyc_limits(
+    &mut self,
+    kyc_limits: [u64; KYC_TIERS],
+  ) -> Result<()> {
+    self.kyc_limits = kyc_limits;
+
+    Ok(())
+  }
+
+  pub fn add_admin(
+    &mut self,
+    admin: Pubkey,
+    roles: u8,
+  ) -> Result<()> {
+    if self.get_admin(&admin).is_some() {
+      return err!(errors::Presale::AdminExists);
+    }
+
+    if self.admins.len() >= Self::MAX_ADMINS {
+      return err!(errors::Presale::TooManyAdmins);
+    }
+
+    if roles & !ALL_ROLES != 0 {
+      return err!(errors::Presale::UnknownRole);
+    }
+
+    self.admins.push(Admin { key: admin, roles });
+
+    Ok(())
+  }
+
+  pub fn remove_admin(
+    &mut self,
+    admin: Pubkey,
+  ) -> Result<()> {
+    let index = self.admins.iter().position(|entry| entry.key == admin);
+    match index {
+      Some(index) => self.admins.remove(index),
+      None => return err!(errors::Presale::AdminNotFound),
+    };
+
+    Ok(())
+  }
+
+  pub fn grant_roles(
+    &mut self,
+    admin: Pubkey,
+    roles: u8,
+  ) -> Result<()> {
+    if roles & !ALL_ROLES != 0 {
+      return err!(errors::Presale::UnknownRole);
+    }
+
+    match self.admins.iter_mut().find(|entry| entry.key == admin) {
+      Some(entry) => entry.roles |= roles,
+      None => return err!(errors::Presale::AdminNotFound),
+    };
+
+    Ok(())
+  }
+
+  pub fn revoke_roles(
+    &mut self,
+    admin: Pubkey,
+    roles: u8,
+  ) -> Result<()> {
+    match self.admins.iter_mut().find(|entry| entry.key == admin) {
+      Some(entry) => entry.roles &= !roles,
+      None => return err!(errors::Presale::AdminNotFound),
+    };
+
+    Ok(())
+  }
+
+  pub fn propose_owner(
+    &mut self,
+    owner: Pubkey,
+  ) -> Result<()> {
+    self.pending_owner = owner;
+
+    Ok(())
+  }
+
+  pub fn accept_owner(
+    &mut self,
+  ) -> Result<()> {
+    if self.pending_owner == Pubkey::default() {
+      return err!(errors::Presale::NoPendingOwner);
+    }
+
+    self.owner = self.pending_owner;
+    self.pending_owner = Pubkey::default();
+
+    Ok(())
+  }
+
+  pub fn get_owner(
+    &self,
+  ) -> Pubkey {
+    self.owner
+  }
+
+  pub fn get_pending_owner(
+    &self,
+  ) -> Pubkey {
+    self.pending_owner
+  }
+
+  pub fn get_store(
+    &self,
+  ) -> Pubkey {
+    self.store
+  }
+
+  pub fn get_signer(
+    &self,
+  ) -> Pubkey {
+    self.signer
+  }
+
+  pub fn get_oracle_policy(
+    &self,
+  ) -> OraclePolicy {
+    self.oracle_policy.clone()
+  }
+
+  pub fn get_kyc_signer(
+    &self,
+  ) -> Pubkey {
+    self.kyc_signer
+  }
+
+  pub fn get_kyc_limit(
+    &self,
+    tier: u8,
+  ) -> u128 {
+    u128::from(self.kyc_limits[usize::from(tier)])
+  }
+
+  pub fn get_quote_band(
+    &self,
+  ) -> u64 {
+    self.quote_band
+  }
+
+  pub fn is_owner(
+    &self,
+    address: &Pubkey,
+  ) -> bool {
+    &self.owner == address
+  }
+
+  pub fn get_admin(
+    &self,
+    address: &Pubkey,
+  ) -> Option<&Admin> {
+    self.admins.iter().find(|entry| &entry.key == address)
+  }
+
+  pub fn get_roles(
+    &self,
+    address: &Pubkey,
+  ) -> u8 {
+    if self.is_owner(address) {
+      return ALL_ROLES;
+    }
+
+    match self.get_admin(address) {
+      Some(entry) => entry.roles,
+      None => 0,
+    }
+  }
+
+  pub fn has_role(
+    &self,
+    address: &Pubkey,
+    role: u8,
+  ) -> bool {
+    self.get_roles(address) & role == role
+  }
+}
@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+
+/// Holds the payments of one currency while the presale is below its soft cap.
+/// SOL is kept as lamports of this account, tokens in its associated token account
+#[account]
+pub struct Escrow {
+  mint: Pubkey,
+  deposited: u64,
+}
+
+impl Escrow {
+  pub const MAX_SIZE: usize = 32 + 8;
+
+  pub fn init(
+    &mut self,
+    mint: Pubkey,
+  ) -> Result<()> {
+    self.mint = mint;
+    self.deposited = 0;
+
+    Ok(())
+  }
+
+  /// Only the treasury share is counted, adviser shares stay in escrow until claimed
+  pub fn deposit(
+    &mut self,
+    amount: u64,
+  ) -> Result<()> {
+    self.deposited += amount;
+
+    Ok(())
+  }
+
+  pub fn reset_deposited(
+    &mut self,
+  ) -> Result<()> {
+    self.deposited = 0;
+
+    Ok(())
+  }
+
+  pub fn get_mint(
+    &self,
+  ) -> Pubkey {
+    self.mint
+  }
+
+  pub fn get_deposited(
+    &self,
+  ) -> u64 {
+    self.deposited
+  }
+}
+
+#[account]
+pub struct Contribution {
+  buyer: Pubkey,
+  mint: Pubkey,
+  amount: u64,
+
+  // What the payments added to the buyer and presale state, so a refund can take it back
+  usd_amount: u128,
+  token_amount: u128,
+  released_amount: u128,
+}
+
+impl Contribution {
+  pub const MAX_SIZE: usize = (2 * 32) + 8 + (3 * 16);
+
+  /// Records a payment of `amount` worth `usd_amount`, crediting the buyer with `token_amount`
+  /// and releasing `released_amount` from the vault including the adviser share
+  pub fn add(
+    &mut self,
+    buyer: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    usd_amount: u128,
+    token_amount: u128,
+    released_amount: u128,
+  ) -> Result<()> {
+    self.buyer = buyer;
+    self.mint = mint;
+    self.amount += amount;
+    self.usd_amount += usd_amount;
+    self.token_amount += token_amount;
+    self.released_amount += released_amount;
+
+    Ok(())
+  }
+
+  pub fn get_amount(
+    &self,
+  ) -> u64 {
+    self.amount
+  }
+
+  pub fn get_usd_amount(
+    &self,
+  ) -> u128 {
+    self.usd_amount
+  }
+
+  pub fn get_token_amount(
+    &self,
+  ) -> u128 {
+    self.token_amount
+  }
+
+  pub fn get_released_amount(
+    &self,
+  ) -> u128 {
+    self.released_amount
+  }
+}
@@ -15,10 +15,22 @@ pub struct Iteration {
   sold: u128,
   total: u128,
   status: Status,
+
+  start_ts: i64,
+  end_ts: i64,
+
+  next_id: i16,
+
+  min_buy: u64,
+  max_buy: u64,
+
+  merkle_root: [u8; 32],
 }
 
 impl Iteration {
-  pub const MAX_SIZE: usize = 2 + 8 + (2 * 16) + (32 + 1) + 2;
+  /// Size of accounts created by the first release, before schedules, chaining, limits and allowlists
+  pub const LEGACY_SIZE: usize = 2 + 8 + (2 * 16) + (32 + 1) + 2;
+  pub const MAX_SIZE: usize = 2 + 8 + (2 * 16) + (32 + 1) + 2 + (2 * 8) + 2 + (2 * 8) + 32;
 
   pub fn init(
     &mut self,
@@ -31,6 +43,23 @@ impl Iteration {
     self.total = total;
     self.sold = 0;
     self.status = Status::None;
+    self.start_ts = 0;
+    self.end_ts = 0;
+    self.next_id = -1;
+    self.min_buy = 0;
+    self.max_buy = 0;
+    self.merkle_root = [0; 32];
+
+    Ok(())
+  }
+
+  /// Upgrades a reallocated account of the first release, the new fields read as zero
+  /// which leaves it unscheduled and without limits or allowlist
+  pub fn migrate(
+    &mut self,
+  ) -> Result<()> {
+    // A zero would chain it to iteration 0
+    self.next_id = -1;
 
     Ok(())
   }
@@ -61,6 +90,62 @@ impl Iteration {
     Ok(())
   }
 
+  /// Overrides the presale per transaction limits (in USD) while this iteration is active, zero keeps the presale limit
+  pub fn set_buy_limits(
+    &mut self,
+    min_buy: u64,
+    max_buy: u64,
+  ) -> Result<()> {
+    if max_buy != 0 && max_buy < min_buy {
+      return err!(errors::Presale::InvalidBuyLimits);
+    }
+
+    self.min_buy = min_buy;
+    self.max_buy = max_buy;
+
+    Ok(())
+  }
+
+  /// A non-zero root restricts buys to wallets registered with a proof of their allocation
+  pub fn set_merkle_root(
+    &mut self,
+    merkle_root: [u8; 32],
+  ) -> Result<()> {
+    self.merkle_root = merkle_root;
+
+    Ok(())
+  }
+
+  /// A zero timestamp leaves that side of the schedule unbounded
+  pub fn set_schedule(
+    &mut self,
+    start_ts: i64,
+    end_ts: i64,
+  ) -> Result<()> {
+    if start_ts < 0 || end_ts < 0 || (end_ts != 0 && end_ts <= start_ts) {
+      return err!(errors::Presale::InvalidIterationSchedule);
+    }
+
+    self.start_ts = start_ts;
+    self.end_ts = end_ts;
+
+    Ok(())
+  }
+
+  /// The iteration that opens once this one sells out, `-1` ends the chain
+  pub fn set_next_id(
+    &mut self,
+    next_id: i16,
+  ) -> Result<()> {
+    if next_id == self.id || next_id < -1 {
+      return err!(errors::Presale::InvalidIterationChain);
+    }
+
+    self.next_id = next_id;
+
+    Ok(())
+  }
+
   pub fn open(
     &mut self,
   ) -> Result<()> {
@@ -95,32 +180,88 @@ impl Iteration {
   }
 
   pub fn get_id(
-    &mut self,
+    &self,
   ) -> i16 {
     self.id
   }
 
   pub fn get_price(
-    &mut self,
+    &self,
   ) -> u64 {
     self.price
   }
 
   pub fn get_sold(
-    &mut self,
+    &self,
   ) -> u128 {
     self.sold
   }
 
   pub fn get_total(
-    &mut self,
+    &self,
   ) -> u128 {
     self.total
   }
 
+  pub fn get_next_id(
+    &self,
+  ) -> i16 {
+    self.next_id
+  }
+
+  pub fn get_merkle_root(
+    &self,
+  ) -> [u8; 32] {
+    self.merkle_root
+  }
+
+  pub fn is_gated(
+    &self,
+  ) -> bool {
+    self.merkle_root != [0; 32]
+  }
+
+  pub fn is_sold_out(
+    &self,
+  ) -> bool {
+    self.sold >= self.total
+  }
+
+  pub fn get_schedule(
+    &self,
+  ) -> (i64, i64) {
+    (self.start_ts, self.end_ts)
+  }
+
   pub fn is_open(
     &self,
   ) -> bool {
     self.status == Status::Open
   }
+
+  pub fn has_started(
+    &self,
+    now: i64,
+  ) -> bool {
+    now >= self.start_ts
+  }
+
+  pub fn has_ended(
+    &self,
+    now: i64,
+  ) -> bool {
+    self.end_ts != 0 && now >= self.end_ts
+  }
+
+  pub fn get_min_buy(
+    &self,
+  ) -> u128 {
+    u128::from(self.min_buy)
+  }
+
+  pub fn get_max_buy(
+    &self,
+  ) -> u128 {
+    u128::from(self.max_buy)
+  }
 }
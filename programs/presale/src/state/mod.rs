@@ -2,3 +2,8 @@ pub mod presale;
 pub mod iteration;
 pub mod adviser;
 pub mod buyer;
+pub mod config;
+pub mod accepted_mint;
+pub mod price_attestation;
+pub mod escrow;
+pub mod blocklist;
@@ -1,11 +1,44 @@
 use anchor_lang::prelude::*;
 use crate::errors;
+use crate::config::{ ALL_PAUSE_FLAGS, MAX_BONUS_TIERS, PRECISION };
 
 #[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
 pub enum Status {
   None,
   Closed,
   Open,
+  Paused,
+}
+
+/// What happens to the unsold supply of an iteration when the presale moves to the next one
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub enum LeftoverPolicy {
+  Discard,
+  Rollover,
+  Reserve,
+  Burn,
+}
+
+/// Extra tokens, as a `percent` of the bought amount, for buys of at least `threshold` USD
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct BonusTier {
+  pub threshold: u64,
+  pub percent: u64,
+}
+
+impl BonusTier {
+  pub const MAX_SIZE: usize = 2 * 8;
+}
+
+#[derive(Clone, PartialEq, AnchorDeserialize, AnchorSerialize)]
+pub struct Vesting {
+  tge_percent: u64,
+  cliff: i64,
+  duration: i64,
+}
+
+impl Vesting {
+  pub const MAX_SIZE: usize = 3 * 8;
 }
 
 #[account]
@@ -16,10 +49,42 @@ pub struct Presale {
   total_released: u128,
   iteration: i16,
   status: Status,
+
+  token_mint: Pubkey,
+  claims_enabled: bool,
+
+  vesting: Vesting,
+  tge_ts: i64,
+
+  pause_flags: u8,
+
+  leftover_policy: LeftoverPolicy,
+  hard_cap: u128,
+  reserved: u128,
+  burned: u128,
+  // Parts of `reserved` and `burned` already moved out of the vault
+  reserve_withdrawn: u128,
+  burn_executed: u128,
+
+  soft_cap: u128,
+  raised: u128,
+  escrow_released: bool,
+  // Set by the first refund, iteration and adviser credits are not reversed so the escrow can no longer be released
+  escrow_refunded: bool,
+
+  max_wallet_buy: u64,
+  max_tx_buy: u64,
+
+  bonus_tiers: Vec<BonusTier>,
+
+  buyer_discount: u64,
 }
 
 impl Presale {
-  pub const MAX_SIZE: usize = (4 * 8) + 16 + 2 + (32 + 1) * 1;
+  /// Size of accounts created by the first release, which only tracked sales
+  pub const LEGACY_SIZE: usize = (4 * 8) + 16 + 2 + (32 + 1) * 1;
+  pub const MAX_SIZE: usize = (4 * 8) + 16 + 2 + (32 + 1) * 1 + 32 + 1 + Vesting::MAX_SIZE + 8 + 1 + 1 + (3 * 16) + (2 * 16) + 1 + 1 + (2 * 8)
+    + 4 + (BonusTier::MAX_SIZE * MAX_BONUS_TIERS) + 8 + (2 * 16);
 
   pub fn init(
     &mut self,
@@ -33,6 +98,51 @@ impl Presale {
     self.total_released = 0;
     self.status = Status::None;
 
+    self.token_mint = Pubkey::default();
+    self.claims_enabled = false;
+
+    self.vesting = Vesting {
+      tge_percent: 1000_000_000, // 100%
+      cliff: 0,
+      duration: 0,
+    };
+    self.tge_ts = 0;
+
+    self.pause_flags = 0;
+
+    self.leftover_policy = LeftoverPolicy::Discard;
+    self.hard_cap = 0;
+    self.reserved = 0;
+    self.burned = 0;
+    self.reserve_withdrawn = 0;
+    self.burn_executed = 0;
+
+    self.soft_cap = 0;
+    self.raised = 0;
+    self.escrow_released = false;
+    self.escrow_refunded = false;
+
+    self.max_wallet_buy = 0;
+    self.max_tx_buy = 0;
+
+    self.bonus_tiers = Vec::new();
+    self.buyer_discount = 0;
+
+    Ok(())
+  }
+
+  /// Upgrades a reallocated account of the first release. The new fields read as zero,
+  /// which matches their initial values apart from the vesting schedule
+  pub fn migrate(
+    &mut self,
+  ) -> Result<()> {
+    // Presales started before vesting existed keep releasing everything at TGE
+    self.vesting = Vesting {
+      tge_percent: 1000_000_000, // 100%
+      cliff: 0,
+      duration: 0,
+    };
+
     Ok(())
   }
 
@@ -45,6 +155,61 @@ impl Presale {
     Ok(())
   }
 
+  /// Limits are in USD, zero leaves them unbounded
+  pub fn set_buy_limits(
+    &mut self,
+    max_wallet_buy: u64,
+    max_tx_buy: u64,
+  ) -> Result<()> {
+    self.max_wallet_buy = max_wallet_buy;
+    self.max_tx_buy = max_tx_buy;
+
+    Ok(())
+  }
+
+  /// Adds or updates the tier at `threshold`, a zero percent removes it. Tiers are kept ordered by threshold
+  pub fn set_bonus(
+    &mut self,
+    threshold: u64,
+    percent: u64,
+  ) -> Result<()> {
+    if percent > 10u64.pow(PRECISION) {
+      return err!(errors::Presale::InvalidBonusPercent);
+    }
+
+    let position = self.bonus_tiers.binary_search_by_key(&threshold, |tier| tier.threshold);
+    match (position, percent) {
+      (Ok(index), 0) => {
+        self.bonus_tiers.remove(index);
+      }
+      (Ok(index), _) => self.bonus_tiers[index].percent = percent,
+      (Err(_), 0) => return err!(errors::Presale::BonusTierNotFound),
+      (Err(index), _) => {
+        if self.bonus_tiers.len() >= MAX_BONUS_TIERS {
+          return err!(errors::Presale::TooManyBonusTiers);
+        }
+        self.bonus_tiers.insert(index, BonusTier { threshold, percent });
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Default extra tokens, as a percent, for buys using an adviser code, paid out of the adviser token share.
+  /// Advisers may set a higher one
+  pub fn set_buyer_discount(
+    &mut self,
+    buyer_discount: u64,
+  ) -> Result<()> {
+    if buyer_discount > 10u64.pow(PRECISION) {
+      return err!(errors::Presale::InvalidBuyerDiscount);
+    }
+
+    self.buyer_discount = buyer_discount;
+
+    Ok(())
+  }
+
   pub fn set_percents(
     &mut self,
     c: u64,
@@ -71,6 +236,11 @@ impl Presale {
       return err!(errors::Presale::PresaleOpen);
     }
 
+    // Only the pauser may resume a paused presale
+    if self.status == Status::Paused {
+      return err!(errors::Presale::PresalePaused);
+    }
+
     self.status = Status::Open;
 
     Ok(())
@@ -79,7 +249,7 @@ impl Presale {
   pub fn close_presale(
     &mut self,
   ) -> Result<()> {
-    if self.status != Status::Open {
+    if self.status != Status::Open && self.status != Status::Paused {
       return err!(errors::Presale::PresaleClosed);
     }
 
@@ -88,6 +258,184 @@ impl Presale {
     Ok(())
   }
 
+  pub fn pause(
+    &mut self,
+  ) -> Result<()> {
+    if self.status != Status::Open {
+      return err!(errors::Presale::PresaleNotEnabled);
+    }
+
+    self.status = Status::Paused;
+
+    Ok(())
+  }
+
+  pub fn unpause(
+    &mut self,
+  ) -> Result<()> {
+    if self.status != Status::Paused {
+      return err!(errors::Presale::PresaleNotPaused);
+    }
+
+    self.status = Status::Open;
+
+    Ok(())
+  }
+
+  pub fn pause_subsystems(
+    &mut self,
+    flags: u8,
+  ) -> Result<()> {
+    if flags & !ALL_PAUSE_FLAGS != 0 {
+      return err!(errors::Presale::UnknownPauseFlag);
+    }
+
+    self.pause_flags |= flags;
+
+    Ok(())
+  }
+
+  pub fn unpause_subsystems(
+    &mut self,
+    flags: u8,
+  ) -> Result<()> {
+    if flags & !ALL_PAUSE_FLAGS != 0 {
+      return err!(errors::Presale::UnknownPauseFlag);
+    }
+
+    self.pause_flags &= !flags;
+
+    Ok(())
+  }
+
+  pub fn set_leftover_policy(
+    &mut self,
+    leftover_policy: LeftoverPolicy,
+  ) -> Result<()> {
+    self.leftover_policy = leftover_policy;
+
+    Ok(())
+  }
+
+  /// A zero hard cap leaves the presale uncapped
+  pub fn set_hard_cap(
+    &mut self,
+    hard_cap: u128,
+  ) -> Result<()> {
+    if hard_cap != 0 && hard_cap < self.total_released {
+      return err!(errors::Presale::HardCapTooSmall);
+    }
+
+    self.hard_cap = hard_cap;
+
+    Ok(())
+  }
+
+  /// A non-zero soft cap (in USD) puts the presale in escrow mode, it can only be set before opening
+  pub fn set_soft_cap(
+    &mut self,
+    soft_cap: u128,
+  ) -> Result<()> {
+    if self.status != Status::None {
+      return err!(errors::Presale::PresaleOpen);
+    }
+
+    self.soft_cap = soft_cap;
+
+    Ok(())
+  }
+
+  pub fn add_raised(
+    &mut self,
+    usd_amount: u128,
+  ) -> Result<()> {
+    self.raised += usd_amount;
+
+    Ok(())
+  }
+
+  pub fn release_escrow(
+    &mut self,
+  ) -> Result<()> {
+    if self.escrow_refunded {
+      return err!(errors::Presale::EscrowRefunded);
+    }
+
+    if self.raised < self.soft_cap {
+      return err!(errors::Presale::SoftCapNotReached);
+    }
+
+    self.escrow_released = true;
+
+    Ok(())
+  }
+
+  /// Takes a refunded contribution back out of the raised and released totals
+  pub fn refund(
+    &mut self,
+    usd_amount: u128,
+    released_amount: u128,
+  ) -> Result<()> {
+    self.raised = self.raised.saturating_sub(usd_amount);
+    self.total_released = self.total_released.saturating_sub(released_amount);
+    self.escrow_refunded = true;
+
+    Ok(())
+  }
+
+  /// Applies the leftover policy to `unsold` tokens and returns the amount rolled into the next iteration
+  /// Reserved and burned supply stays in the vault until `withdraw_reserve` or `burn_leftover` moves it out
+  pub fn settle_leftover(
+    &mut self,
+    unsold: u128,
+    can_rollover: bool,
+  ) -> Result<u128> {
+    match self.leftover_policy {
+      LeftoverPolicy::Discard => Ok(0),
+      LeftoverPolicy::Rollover if can_rollover => Ok(unsold),
+      // Without a next iteration there is nothing to roll into, so the supply goes to the reserve
+      LeftoverPolicy::Rollover | LeftoverPolicy::Reserve => {
+        self.reserved += unsold;
+        Ok(0)
+      }
+      LeftoverPolicy::Burn => {
+        self.burned += unsold;
+        if self.hard_cap != 0 {
+          self.hard_cap = self.hard_cap.saturating_sub(unsold);
+        }
+        Ok(0)
+      }
+    }
+  }
+
+  /// Marks the reserved supply not yet withdrawn as withdrawn and returns it
+  pub fn withdraw_reserve(
+    &mut self,
+  ) -> Result<u128> {
+    let amount = self.reserved - self.reserve_withdrawn;
+    if amount == 0 {
+      return err!(errors::Presale::NothingToSettle);
+    }
+
+    self.reserve_withdrawn = self.reserved;
+
+    Ok(amount)
+  }
+
+  /// Marks the supply settled for burning but not yet burned as burned and returns it
+  pub fn execute_burn(
+    &mut self,
+  ) -> Result<u128> {
+    let amount = self.burned - self.burn_executed;
+    if amount == 0 {
+      return err!(errors::Presale::NothingToSettle);
+    }
+
+    self.burn_executed = self.burned;
+
+    Ok(amount)
+  }
+
   pub fn set_iteration(
     &mut self,
     iteration: i16,
@@ -97,6 +445,60 @@ impl Presale {
     Ok(())
   }
 
+  pub fn set_token_mint(
+    &mut self,
+    mint: Pubkey,
+  ) -> Result<()> {
+    if self.claims_enabled {
+      return err!(errors::Presale::ClaimsEnabled);
+    }
+
+    self.token_mint = mint;
+
+    Ok(())
+  }
+
+  pub fn set_vesting(
+    &mut self,
+    tge_percent: u64,
+    cliff: i64,
+    duration: i64,
+  ) -> Result<()> {
+    if self.claims_enabled {
+      return err!(errors::Presale::ClaimsEnabled);
+    }
+
+    if tge_percent > 1000_000_000 {
+      return err!(errors::Presale::VestingPercentTooLarge);
+    }
+
+    if cliff < 0 || duration < 0 {
+      return err!(errors::Presale::InvalidVestingSchedule);
+    }
+
+    self.vesting = Vesting { tge_percent, cliff, duration };
+
+    Ok(())
+  }
+
+  pub fn enable_claims(
+    &mut self,
+    now: i64,
+  ) -> Result<()> {
+    if self.token_mint == Pubkey::default() {
+      return err!(errors::Presale::TokenMintNotSet);
+    }
+
+    if self.claims_enabled {
+      return err!(errors::Presale::ClaimsEnabled);
+    }
+
+    self.claims_enabled = true;
+    self.tge_ts = now;
+
+    Ok(())
+  }
+
   pub fn add_sold(
     &mut self,
     amount: u128,
@@ -118,21 +520,165 @@ impl Presale {
     u128::from(self.min_buy)
   }
 
+  pub fn get_max_wallet_buy(
+    &self,
+  ) -> u128 {
+    u128::from(self.max_wallet_buy)
+  }
+
+  pub fn get_max_tx_buy(
+    &self,
+  ) -> u128 {
+    u128::from(self.max_tx_buy)
+  }
+
   pub fn get_total_released(
     &self,
   ) -> u128 {
     self.total_released
   }
 
+  /// Bonus percent of the highest tier reached by a buyer volume of `usd_amount`
+  pub fn get_bonus_percent(
+    &self,
+    usd_amount: u128,
+  ) -> u64 {
+    self.bonus_tiers.iter()
+      .rev()
+      .find(|tier| u128::from(tier.threshold) <= usd_amount)
+      .map_or(0, |tier| tier.percent)
+  }
+
+  pub fn get_buyer_discount(
+    &self,
+  ) -> u64 {
+    self.buyer_discount
+  }
+
   pub fn get_percents(
     &mut self,
   ) -> (u64, u64) {
     (self.c_percent, self.t_percent)
   }
 
+  pub fn get_vested(
+    &self,
+    balance: u128,
+    now: i64,
+  ) -> u128 {
+    if !self.claims_enabled || now < self.tge_ts {
+      return 0;
+    }
+
+    let unlocked = balance * u128::from(self.vesting.tge_percent) / 1000_000_000;
+    let cliff_end = self.tge_ts.saturating_add(self.vesting.cliff);
+    if now < cliff_end {
+      return unlocked;
+    }
+
+    let elapsed = now - cliff_end;
+    if elapsed >= self.vesting.duration {
+      return balance;
+    }
+
+    // Linear release of the locked part after the cliff
+    let locked = balance - unlocked;
+    let elapsed = u128::try_from(elapsed).unwrap();
+    let duration = u128::try_from(self.vesting.duration).unwrap();
+    unlocked + locked * elapsed / duration
+  }
+
+  pub fn get_token_mint(
+    &self,
+  ) -> Pubkey {
+    self.token_mint
+  }
+
   pub fn is_open(
     &self,
   ) -> bool {
     self.status == Status::Open
   }
+
+  pub fn get_soft_cap(
+    &self,
+  ) -> u128 {
+    self.soft_cap
+  }
+
+  pub fn get_raised(
+    &self,
+  ) -> u128 {
+    self.raised
+  }
+
+  pub fn is_escrow(
+    &self,
+  ) -> bool {
+    self.soft_cap != 0
+  }
+
+  /// Payments are held in escrow and nothing can be claimed until the soft cap is reached
+  pub fn is_escrow_pending(
+    &self,
+  ) -> bool {
+    self.is_escrow() && !self.escrow_released
+  }
+
+  pub fn is_refundable(
+    &self,
+  ) -> bool {
+    self.is_escrow_pending() && self.status == Status::Closed && self.raised < self.soft_cap
+  }
+
+  pub fn get_hard_cap(
+    &self,
+  ) -> u128 {
+    self.hard_cap
+  }
+
+  pub fn get_reserved(
+    &self,
+  ) -> u128 {
+    self.reserved
+  }
+
+  pub fn get_burned(
+    &self,
+  ) -> u128 {
+    self.burned
+  }
+
+  pub fn is_hard_cap_exceeded(
+    &self,
+    amount: u128,
+  ) -> bool {
+    self.hard_cap != 0 && self.total_released + amount > self.hard_cap
+  }
+
+  pub fn get_leftover_policy(
+    &self,
+  ) -> LeftoverPolicy {
+    self.leftover_policy.clone()
+  }
+
+  pub fn get_pause_flags(
+    &self,
+  ) -> u8 {
+    self.pause_flags
+  }
+
+  /// A paused presale halts every subsystem, otherwise only the flagged ones are halted
+  pub fn is_paused(
+    &self,
+    flag: u8,
+  ) -> bool {
+    self.status == Status::Paused || self.pause_flags & flag != 0
+  }
+
+  pub fn is_claims_enabled(
+    &self,
+  ) -> bool {
+    self.claims_enabled
+  }
 }
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use crate::errors;
+
+#[account]
+pub struct PriceAttestation {
+  feed_id: [u8; 32],
+  price: u64,
+  expo: u32,
+  publish_time: i64,
+  attester: Pubkey,
+}
+
+impl PriceAttestation {
+  pub const MAX_SIZE: usize = 32 + 8 + 4 + 8 + 32;
+
+  pub fn set_price(
+    &mut self,
+    feed_id: [u8; 32],
+    price: u64,
+    expo: u32,
+    publish_time: i64,
+    attester: Pubkey,
+  ) -> Result<()> {
+    if price == 0 || expo > 18 {
+      return err!(errors::Presale::InvalidPriceAttestation);
+    }
+
+    self.feed_id = feed_id;
+    self.price = price;
+    self.expo = expo;
+    self.publish_time = publish_time;
+    self.attester = attester;
+
+    Ok(())
+  }
+
+  pub fn get_feed_id(
+    &self,
+  ) -> [u8; 32] {
+    self.feed_id
+  }
+
+  pub fn get_price(
+    &self,
+  ) -> (u64, u32) {
+    (self.price, self.expo)
+  }
+
+  pub fn get_publish_time(
+    &self,
+  ) -> i64 {
+    self.publish_time
+  }
+}
@@ -0,0 +1,97 @@
+import { expect } from 'chai';
+import * as anchor from '@coral-xyz/anchor';
+import { PublicKey } from '@solana/web3.js';
+import { readFileSync } from 'fs';
+import { start, AddedAccount, ProgramTestContext } from 'solana-bankrun';
+import { BankrunProvider } from 'anchor-bankrun';
+import { Presale } from '../target/types/presale';
+
+// Scenarios that need a presale of their own run against separate in-process banks,
+// since the presale lives in the singleton PDA of the program
+
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111');
+export const PRICE_UPDATE = new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE');
+
+export const idl = JSON.parse(readFileSync('target/idl/presale.json', 'utf8'));
+export const programId = new PublicKey(idl.address);
+export const [programDataPda,] = PublicKey.findProgramAddressSync([programId.toBuffer()], BPF_LOADER_UPGRADEABLE_ID);
+
+const LAMPORTS = anchor.web3.LAMPORTS_PER_SOL;
+
+export function i16ToBytesLE(value: number): Uint8Array {
+  const buffer = new ArrayBuffer(2);
+  const view = new DataView(buffer);
+  view.setInt16(0, value, true);
+  return new Uint8Array(buffer);
+}
+
+// The program is deployed as upgradeable by `owner`, so it can init the config
+function getProgramAccounts(owner: PublicKey): AddedAccount[] {
+  const programAccount = Buffer.alloc(36);
+  programAccount.writeUInt32LE(2, 0);
+  programDataPda.toBuffer().copy(programAccount, 4);
+
+  const programDataHeader = Buffer.alloc(45);
+  programDataHeader.writeUInt32LE(3, 0);
+  programDataHeader.writeUInt8(1, 12);
+  owner.toBuffer().copy(programDataHeader, 13);
+  const programData = Buffer.concat([programDataHeader, readFileSync('target/deploy/presale.so')]);
+
+  return [
+    { address: programId, info: { lamports: LAMPORTS, data: programAccount, owner: BPF_LOADER_UPGRADEABLE_ID, executable: true } },
+    { address: programDataPda, info: { lamports: 100 * LAMPORTS, data: programData, owner: BPF_LOADER_UPGRADEABLE_ID, executable: false } },
+  ];
+}
+
+function getPriceUpdateAccount(): AddedAccount {
+  const fixture = JSON.parse(readFileSync('tests/fixtures/sol_usd_price_update.json', 'utf8'));
+  return {
+    address: PRICE_UPDATE,
+    info: {
+      lamports: fixture.account.lamports,
+      data: Buffer.from(fixture.account.data[0], 'base64'),
+      owner: new PublicKey(fixture.account.owner),
+      executable: false,
+    },
+  };
+}
+
+function getWalletAccount(wallet: PublicKey): AddedAccount {
+  return { address: wallet, info: { lamports: 100 * LAMPORTS, data: Buffer.alloc(0), owner: anchor.web3.SystemProgram.programId, executable: false } };
+}
+
+// Program account holding `data` after the discriminator of `name`
+export function getProgramAccount(address: PublicKey, name: string, data: Buffer): AddedAccount {
+  const discriminator = Buffer.from(idl.accounts.find((account) => account.name === name).discriminator);
+  return { address, info: { lamports: LAMPORTS, data: Buffer.concat([discriminator, data]), owner: programId, executable: false } };
+}
+
+// Starts a bank with the program, the price fixture, funded `wallets` and any extra `accounts`
+export async function startBank(owner: anchor.web3.Keypair, wallets: PublicKey[], accounts: AddedAccount[] = []) {
+  const context = await start([], [
+    ...getProgramAccounts(owner.publicKey),
+    getPriceUpdateAccount(),
+    ...[owner.publicKey, ...wallets].map(getWalletAccount),
+    ...accounts,
+  ]);
+  const provider = new BankrunProvider(context, new anchor.Wallet(owner));
+  const program = new anchor.Program<Presale>(idl, provider);
+
+  return { context, program };
+}
+
+// Bankrun reports failures without logs, so Anchor surfaces them as a ProgramError.
+// Failed transactions are still recorded, so the bank moves on to a fresh blockhash
+// before the same call can be retried
+export async function expectError(context: ProgramTestContext, action: Promise<unknown>, message: string) {
+  try {
+    await action;
+    expect.fail('Expected action to throw an error');
+  } catch (err) {
+    expect(err.msg).to.equal(message);
+  }
+
+  const slot = await context.banksClient.getSlot();
+  //@ts-ignore
+  context.warpToSlot(slot + 1n);
+}
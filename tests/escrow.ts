@@ -0,0 +1,157 @@
+import { expect } from 'chai';
+import * as anchor from '@coral-xyz/anchor';
+import { PublicKey } from '@solana/web3.js';
+import { ProgramTestContext } from 'solana-bankrun';
+import { Presale } from '../target/types/presale';
+import { PRICE_UPDATE, programId, programDataPda, i16ToBytesLE, startBank, expectError } from './bankrun';
+
+const ROUND_TAG = Buffer.from('ITERATION');
+const USER_TAG = Buffer.from('BUYER');
+const REF_TAG = Buffer.from('ADVISER');
+const ESCROW_TAG = Buffer.from('ESCROW');
+const CONTRIBUTION_TAG = Buffer.from('CONTRIBUTION');
+
+const [presalePda,] = PublicKey.findProgramAddressSync([], programId);
+const [escrowPda,] = PublicKey.findProgramAddressSync([ESCROW_TAG], programId);
+const [iteration1Pda,] = PublicKey.findProgramAddressSync([ROUND_TAG, Buffer.from('_'), i16ToBytesLE(1)], programId);
+const [noAdviserPda,] = PublicKey.findProgramAddressSync([REF_TAG, Buffer.from('_'), Buffer.from('')], programId);
+
+const LAMPORTS = anchor.web3.LAMPORTS_PER_SOL;
+const precision = new anchor.BN(1000000000);
+
+// Release and refunds are exclusive outcomes of a presale, so each scenario runs on its own bank
+describe('Presale escrow', () => {
+  const owner = anchor.web3.Keypair.generate();
+  const alice = anchor.web3.Keypair.generate();
+  const bob = anchor.web3.Keypair.generate();
+  const store = anchor.web3.Keypair.generate().publicKey;
+
+  let context: ProgramTestContext;
+  let program: anchor.Program<Presale>;
+
+  function getBuyerPdas(wallet: PublicKey) {
+    const [buyerPda,] = PublicKey.findProgramAddressSync([USER_TAG, Buffer.from('_'), wallet.toBuffer()], programId);
+    const [contributionPda,] = PublicKey.findProgramAddressSync([CONTRIBUTION_TAG, Buffer.from('_'), wallet.toBuffer()], programId);
+    return { buyerPda, contributionPda };
+  }
+
+  // Opens a presale in escrow mode with a single open iteration priced at $0.32
+  async function setup(softCap: anchor.BN) {
+    ({ context, program } = await startBank(owner, [alice.publicKey, bob.publicKey]));
+
+    await program.methods.initConfig(store, owner.publicKey).accounts({ payer: owner.publicKey, programData: programDataPda }).rpc();
+    // There is no attester here, so buys run on the primary feed alone
+    await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), true).accounts({ payer: owner.publicKey }).rpc();
+    await program.methods.init().accounts({ payer: owner.publicKey }).rpc();
+    await program.methods.setPresaleSoftCap(softCap).accounts({ payer: owner.publicKey, presale: presalePda }).rpc();
+    await program.methods.createIteration(1, new anchor.BN(320000000), new anchor.BN('1000000000000000')).accounts({ payer: owner.publicKey }).rpc();
+    await program.methods.openIteration().accounts({ payer: owner.publicKey, iteration: iteration1Pda, presale: presalePda }).rpc();
+    await program.methods.openPresale().accounts({ payer: owner.publicKey, presale: presalePda }).rpc();
+    await program.methods.initSolEscrow().accounts({ payer: owner.publicKey }).rpc();
+  }
+
+  async function buy(wallet: anchor.web3.Keypair, amount: anchor.BN) {
+    const { buyerPda, contributionPda } = getBuyerPdas(wallet.publicKey);
+    await program.methods
+      .buySol('', amount)
+      .accounts({
+        payer: wallet.publicKey,
+        iteration: iteration1Pda,
+        presale: presalePda,
+        storeInfo: store,
+        priceUpdate: PRICE_UPDATE,
+        priceAttestation: null,
+        escrow: escrowPda,
+        contribution: contributionPda,
+        nextIteration: null,
+        buyer: buyerPda,
+        adviser: noAdviserPda,
+      })
+      .signers([wallet])
+      .rpc();
+  }
+
+  function refund(wallet: anchor.web3.Keypair) {
+    const { buyerPda, contributionPda } = getBuyerPdas(wallet.publicKey);
+    return program.methods
+      .refundSol()
+      .accounts({ payer: wallet.publicKey, presale: presalePda, buyer: buyerPda, escrow: escrowPda, contribution: contributionPda })
+      .signers([wallet])
+      .rpc();
+  }
+
+  it('should hold buys in escrow and release them once the soft cap is reached', async () => {
+    await setup(new anchor.BN(200).mul(precision));
+
+    const amount = new anchor.BN(LAMPORTS);
+    await buy(alice, amount);
+
+    const { buyerPda, contributionPda } = getBuyerPdas(alice.publicKey);
+    let presale = await program.account.presale.fetch(presalePda);
+    let escrow = await program.account.escrow.fetch(escrowPda);
+    const buyer = await program.account.buyer.fetch(buyerPda);
+    const contribution = await program.account.contribution.fetch(contributionPda);
+    const usdAmount = new anchor.BN(144).mul(precision);
+    expect(presale.raised.toString()).to.equal(usdAmount.toString());
+    expect(escrow.deposited.toString()).to.equal(amount.toString());
+    expect(contribution.amount.toString()).to.equal(amount.toString());
+    expect(contribution.usdAmount.toString()).to.equal(usdAmount.toString());
+    expect(contribution.tokenAmount.toString()).to.equal(buyer.balance.toString());
+    expect(contribution.releasedAmount.toString()).to.equal(presale.totalReleased.toString());
+    expect(await context.banksClient.getAccount(store)).to.equal(null);
+
+    const release = () => program.methods
+      .releaseSolEscrow()
+      .accounts({ payer: owner.publicKey, presale: presalePda, escrow: escrowPda, storeInfo: store })
+      .rpc();
+    await expectError(context, release(), 'Presale soft cap not reached');
+
+    await buy(alice, amount.divn(2));
+    await release();
+
+    presale = await program.account.presale.fetch(presalePda);
+    escrow = await program.account.escrow.fetch(escrowPda);
+    expect(presale.escrowReleased).to.equal(true);
+    expect(escrow.deposited.toString()).to.equal('0');
+    expect((await context.banksClient.getBalance(store)).toString()).to.equal(amount.muln(3).divn(2).toString());
+
+    await expectError(context, refund(alice), 'Presale not refundable');
+  });
+
+  it('should refund escrowed buys when the presale closes below the soft cap', async () => {
+    await setup(new anchor.BN(1000).mul(precision));
+
+    const amount = new anchor.BN(LAMPORTS);
+    await buy(alice, amount);
+    await buy(bob, amount.divn(2));
+
+    await expectError(context, refund(bob), 'Presale not refundable');
+    await program.methods.closePresale().accounts({ payer: owner.publicKey, presale: presalePda }).rpc();
+
+    const { buyerPda, contributionPda } = getBuyerPdas(alice.publicKey);
+    const bobBuyer = await program.account.buyer.fetch(getBuyerPdas(bob.publicKey).buyerPda);
+    const escrowBefore = await context.banksClient.getBalance(escrowPda);
+    await refund(alice);
+
+    const presale = await program.account.presale.fetch(presalePda);
+    const buyer = await program.account.buyer.fetch(buyerPda);
+    const escrowAfter = await context.banksClient.getBalance(escrowPda);
+    expect((escrowBefore - escrowAfter).toString()).to.equal(amount.toString());
+    expect(await program.account.contribution.fetchNullable(contributionPda)).to.equal(null);
+
+    // Only the share of the refunded buyer is taken back out of the presale
+    expect(buyer.balance.toString()).to.equal('0');
+    expect(buyer.usdAmount.toString()).to.equal('0');
+    expect(buyer.paid.length).to.equal(0);
+    expect(buyer.iterations.length).to.equal(0);
+    expect(presale.raised.toString()).to.equal(new anchor.BN(72).mul(precision).toString());
+    expect(presale.totalReleased.toString()).to.equal(bobBuyer.balance.toString());
+
+    // Iteration and adviser credits of refunded buys are kept, so the escrow stays locked for good
+    await expectError(
+      context,
+      program.methods.releaseSolEscrow().accounts({ payer: owner.publicKey, presale: presalePda, escrow: escrowPda, storeInfo: store }).rpc(),
+      'Escrow already refunded',
+    );
+  });
+});
@@ -0,0 +1,183 @@
+import { expect } from 'chai';
+import * as anchor from '@coral-xyz/anchor';
+import { PublicKey } from '@solana/web3.js';
+import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { PRICE_UPDATE, programId, programDataPda, i16ToBytesLE, getProgramAccount, startBank, expectError } from './bankrun';
+
+const ROUND_TAG = Buffer.from('ITERATION');
+const REF_TAG = Buffer.from('ADVISER');
+const REWARD_TAG = Buffer.from('REWARD');
+
+const [presalePda,] = PublicKey.findProgramAddressSync([], programId);
+const [iteration1Pda,] = PublicKey.findProgramAddressSync([ROUND_TAG, Buffer.from('_'), i16ToBytesLE(1)], programId);
+
+function writeUint(data: Buffer, value: string, offset: number, length: number) {
+  new anchor.BN(value).toArrayLike(Buffer, 'le', length).copy(data, offset);
+}
+
+// Accounts in the layout of the first release, zero padded up to its declared size
+function getLegacyPresale() {
+  const data = Buffer.alloc(83);
+  writeUint(data, '2000000000', 0, 8); // min buy
+  writeUint(data, '100000000', 8, 8); // c percent
+  writeUint(data, '200000000', 16, 8); // t percent
+  writeUint(data, '5000000000000000000', 24, 16); // total released
+  data.writeInt16LE(1, 40); // iteration
+  data.writeUInt8(2, 42); // open
+  return getProgramAccount(presalePda, 'Presale', data);
+}
+
+function getLegacyIteration() {
+  const data = Buffer.alloc(77);
+  data.writeInt16LE(1, 0); // id
+  writeUint(data, '320000000', 2, 8); // price
+  writeUint(data, '1000000000000000', 10, 16); // sold
+  writeUint(data, '2000000000000000', 26, 16); // total
+  data.writeUInt8(2, 42); // open
+  return getProgramAccount(iteration1Pda, 'Iteration', data);
+}
+
+function getLegacyAdviser(adviserPda: PublicKey) {
+  const data = Buffer.alloc(60);
+  writeUint(data, '50000000', 0, 8); // c percent
+  writeUint(data, '50000000', 8, 8); // t percent
+  writeUint(data, '1000000', 16, 8); // sol reward
+  writeUint(data, '2000000', 24, 8); // usdt reward
+  writeUint(data, '3000000', 32, 8); // usdc reward
+  writeUint(data, '4000000000', 40, 16); // token reward
+  data.writeUInt8(1, 56); // enabled
+  return getProgramAccount(adviserPda, 'Adviser', data);
+}
+
+function getMintAccount(mint: PublicKey, authority: PublicKey) {
+  const data = Buffer.alloc(82);
+  data.writeUInt32LE(1, 0);
+  authority.toBuffer().copy(data, 4);
+  data.writeUInt8(6, 44); // decimals
+  data.writeUInt8(1, 45); // initialized
+  return { address: mint, info: { lamports: anchor.web3.LAMPORTS_PER_SOL, data, owner: TOKEN_PROGRAM_ID, executable: false } };
+}
+
+describe('Presale migrations', () => {
+  const owner = anchor.web3.Keypair.generate();
+
+  it('should be able to migrate presale and iteration accounts of the first release', async () => {
+    const { context, program } = await startBank(owner, [], [getLegacyPresale(), getLegacyIteration()]);
+
+    await program.methods.migratePresale().accounts({ payer: owner.publicKey, presale: presalePda }).rpc();
+    const presale = await program.account.presale.fetch(presalePda);
+    expect(presale.minBuy.toString()).to.equal('2000000000');
+    expect(presale.cPercent.toString()).to.equal('100000000');
+    expect(presale.tPercent.toString()).to.equal('200000000');
+    expect(presale.totalReleased.toString()).to.equal('5000000000000000000');
+    expect(presale.iteration).to.equal(1);
+    expect('open' in presale.status).to.equal(true);
+    expect(presale.vesting.tgePercent.toString()).to.equal('1000000000');
+    expect(presale.hardCap.toString()).to.equal('0');
+    expect(presale.bonusTiers.length).to.equal(0);
+
+    await program.methods.migrateIteration(1).accounts({ payer: owner.publicKey, iteration: iteration1Pda }).rpc();
+    const iteration = await program.account.iteration.fetch(iteration1Pda);
+    expect(iteration.id).to.equal(1);
+    expect(iteration.price.toString()).to.equal('320000000');
+    expect(iteration.sold.toString()).to.equal('1000000000000000');
+    expect(iteration.total.toString()).to.equal('2000000000000000');
+    expect('open' in iteration.status).to.equal(true);
+    expect(iteration.nextId).to.equal(-1);
+    expect(iteration.startTs.toString()).to.equal('0');
+
+    await expectError(context, program.methods.migratePresale().accounts({ payer: owner.publicKey, presale: presalePda }).rpc(), 'Account already migrated');
+    await expectError(context, program.methods.migrateIteration(1).accounts({ payer: owner.publicKey, iteration: iteration1Pda }).rpc(), 'Account already migrated');
+  });
+
+  it('should be able to migrate an adviser of the first release with its stablecoin rewards', async () => {
+    const code = 'XYJ-XYJ';
+    const usdtMint = anchor.web3.Keypair.generate().publicKey;
+    const usdcMint = anchor.web3.Keypair.generate().publicKey;
+    const [adviserPda,] = PublicKey.findProgramAddressSync([REF_TAG, Buffer.from('_'), Buffer.from(code)], programId);
+    const [usdtRewardPda,] = PublicKey.findProgramAddressSync([REWARD_TAG, Buffer.from('_'), Buffer.from(code), Buffer.from('_'), usdtMint.toBuffer()], programId);
+    const [usdcRewardPda,] = PublicKey.findProgramAddressSync([REWARD_TAG, Buffer.from('_'), Buffer.from(code), Buffer.from('_'), usdcMint.toBuffer()], programId);
+
+    const { context, program } = await startBank(owner, [], [
+      getLegacyAdviser(adviserPda),
+      getMintAccount(usdtMint, owner.publicKey),
+      getMintAccount(usdcMint, owner.publicKey),
+    ]);
+    await program.methods.initConfig(owner.publicKey, owner.publicKey).accounts({ payer: owner.publicKey, programData: programDataPda }).rpc();
+
+    const migrate = () => program.methods
+      .migrateAdviser(code)
+      .accounts({ payer: owner.publicKey, adviser: adviserPda, usdtMint, usdcMint, usdtReward: usdtRewardPda, usdcReward: usdcRewardPda })
+      .rpc();
+    await migrate();
+
+    const adviser = await program.account.adviser.fetch(adviserPda);
+    expect(adviser.cPercent.toString()).to.equal('50000000');
+    expect(adviser.tPercent.toString()).to.equal('50000000');
+    expect(adviser.solReward.toString()).to.equal('1000000');
+    expect(adviser.tokenReward.toString()).to.equal('4000000000');
+    expect(adviser.enabled).to.equal(true);
+    expect(adviser.buyerDiscount.toString()).to.equal('0');
+
+    // The stablecoin rewards stay claimable through the per mint reward accounts
+    const usdtReward = await program.account.adviserReward.fetch(usdtRewardPda);
+    const usdcReward = await program.account.adviserReward.fetch(usdcRewardPda);
+    expect(usdtReward.amount.toString()).to.equal('2000000');
+    expect(usdcReward.amount.toString()).to.equal('3000000');
+
+    await expectError(context, migrate(), 'Account already migrated');
+  });
+
+  it('should reject an adviser of the first release until it is migrated', async () => {
+    const code = 'XYJ-XYJ';
+    const alice = anchor.web3.Keypair.generate();
+    const store = anchor.web3.Keypair.generate().publicKey;
+    const usdtMint = anchor.web3.Keypair.generate().publicKey;
+    const usdcMint = anchor.web3.Keypair.generate().publicKey;
+    const [adviserPda,] = PublicKey.findProgramAddressSync([REF_TAG, Buffer.from('_'), Buffer.from(code)], programId);
+
+    const { context, program } = await startBank(owner, [alice.publicKey], [
+      getLegacyAdviser(adviserPda),
+      getMintAccount(usdtMint, owner.publicKey),
+      getMintAccount(usdcMint, owner.publicKey),
+    ]);
+    await program.methods.initConfig(store, owner.publicKey).accounts({ payer: owner.publicKey, programData: programDataPda }).rpc();
+    await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), true).accounts({ payer: owner.publicKey }).rpc();
+    await program.methods.init().accounts({ payer: owner.publicKey }).rpc();
+    await program.methods.createIteration(1, new anchor.BN(320000000), new anchor.BN('1000000000000000')).accounts({ payer: owner.publicKey }).rpc();
+    await program.methods.openIteration().accounts({ payer: owner.publicKey, iteration: iteration1Pda, presale: presalePda }).rpc();
+    await program.methods.openPresale().accounts({ payer: owner.publicKey, presale: presalePda }).rpc();
+
+    const buy = () => program.methods
+      .buySol(code, new anchor.BN(anchor.web3.LAMPORTS_PER_SOL))
+      .accounts({
+        payer: alice.publicKey,
+        iteration: iteration1Pda,
+        presale: presalePda,
+        storeInfo: store,
+        priceUpdate: PRICE_UPDATE,
+        priceAttestation: null,
+        escrow: null,
+        contribution: null,
+        nextIteration: null,
+        adviser: adviserPda,
+      })
+      .signers([alice])
+      .rpc();
+
+    // The legacy bytes would otherwise read as a current adviser with a token reward taken from its stablecoin rewards
+    await expectError(context, buy(), 'A space constraint was violated');
+    await expectError(
+      context,
+      program.methods.claimSol(code, new anchor.BN(0), Array(64).fill(0), 0).accounts({ payer: owner.publicKey, adviser: adviserPda, escrow: null }).rpc(),
+      'Adviser not migrated',
+    );
+    await expectError(context, program.methods.enableAdviser().accounts({ payer: owner.publicKey, adviser: adviserPda }).rpc(), 'Adviser not migrated');
+
+    await program.methods.migrateAdviser(code).accounts({ payer: owner.publicKey, adviser: adviserPda, usdtMint, usdcMint }).rpc();
+    await buy();
+
+    const adviser = await program.account.adviser.fetch(adviserPda);
+    expect(adviser.solReward.gt(new anchor.BN('1000000'))).to.equal(true);
+  });
+});
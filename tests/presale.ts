@@ -3,8 +3,9 @@ import * as anchor from '@coral-xyz/anchor';
 import { PublicKey } from "@solana/web3.js";
 import { Presale } from '../target/types/presale';
 import * as ed from '@noble/ed25519';
+import { keccak_256 } from '@noble/hashes/sha3';
 import { AnchorProvider } from '@coral-xyz/anchor';
-import { createMint, getOrCreateAssociatedTokenAccount, mintTo, getAccount } from '@solana/spl-token';
+import { createMint, getOrCreateAssociatedTokenAccount, getAssociatedTokenAddressSync, mintTo, getAccount } from '@solana/spl-token';
 
 interface StableInfo {
   mint: PublicKey;
@@ -27,6 +28,20 @@ function i16ToBytesLE(value: number): Uint8Array {
 const ROUND_TAG = Buffer.from('ITERATION');
 const USER_TAG = Buffer.from('BUYER');
 const REF_TAG = Buffer.from('ADVISER');
+const VAULT_TAG = Buffer.from('VAULT');
+const CONFIG_TAG = Buffer.from('CONFIG');
+const MINT_TAG = Buffer.from('MINT');
+const REWARD_TAG = Buffer.from('REWARD');
+const ROLE_PRESALE_MANAGER = 1 << 0;
+const ROLE_ITERATION_MANAGER = 1 << 1;
+const ROLE_PRICE_ATTESTER = 1 << 5;
+const PAUSE_BUYS = 1 << 0;
+const PAUSE_ADVISER_CLAIMS = 1 << 1;
+const ATTESTATION_TAG = Buffer.from('ATTESTATION');
+const ESCROW_TAG = Buffer.from('ESCROW');
+const BLOCKLIST_TAG = Buffer.from('BLOCKLIST');
+const SOL_USD_FEED_ID = Buffer.from('ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d', 'hex');
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111');
 
 const prepareStable = async (provider: AnchorProvider, payer: anchor.web3.Keypair, store: PublicKey, keypair: anchor.web3.Keypair): Promise<StableInfo> => {
   try {
@@ -118,6 +133,7 @@ describe("Presale", () => {
     let bob_adviser_code = 'XYB-XYB';
 
     let stables: StablesInfo;
+    let tokenMint: PublicKey;
 
     before(async function () {
       joe_adviser = await generateKeypair();
@@ -127,6 +143,98 @@ describe("Presale", () => {
       stables = await prepareTokens(provider, payer, store);
     });
     
+    it('should not be able to init config if not upgrade authority', async () => {
+      const payer = await generateKeypair();
+      let [programDataPda,] = anchor.web3.PublicKey.findProgramAddressSync([program.programId.toBuffer()], BPF_LOADER_UPGRADEABLE_ID);
+
+      try {
+        await program.methods.initConfig(store, payer.publicKey).accounts({ payer: payer.publicKey, programData: programDataPda }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to init config', async () => {
+      let [programDataPda,] = anchor.web3.PublicKey.findProgramAddressSync([program.programId.toBuffer()], BPF_LOADER_UPGRADEABLE_ID);
+      await program.methods.initConfig(store, payer.publicKey).accounts({ payer: payer.publicKey, programData: programDataPda }).signers([payer]).rpc();
+
+      let [configPda,] = anchor.web3.PublicKey.findProgramAddressSync([CONFIG_TAG], program.programId);
+      const config = await program.account.config.fetch(configPda);
+      expect(config.owner.toString()).to.equal(payer.publicKey.toString());
+      expect(config.admins.length).to.equal(0);
+      expect(config.store.toString()).to.equal(store.toString());
+      expect(config.signer.toString()).to.equal(payer.publicKey.toString());
+    });
+
+    it('should not be able to set store if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      try {
+        await program.methods.setStore(payer.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to set store', async () => {
+      let [configPda,] = anchor.web3.PublicKey.findProgramAddressSync([CONFIG_TAG], program.programId);
+      const other = anchor.web3.Keypair.generate().publicKey;
+
+      await program.methods.setStore(other).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      let config = await program.account.config.fetch(configPda);
+      expect(config.store.toString()).to.equal(other.toString());
+
+      await program.methods.setStore(store).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      config = await program.account.config.fetch(configPda);
+      expect(config.store.toString()).to.equal(store.toString());
+    });
+
+    it('should not be able to set oracle policy if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      try {
+        await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), false).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to set oracle policy', async () => {
+      let [configPda,] = anchor.web3.PublicKey.findProgramAddressSync([CONFIG_TAG], program.programId);
+      let config = await program.account.config.fetch(configPda);
+      expect(config.oraclePolicy.maxAge.toString()).to.equal('60');
+      expect(config.oraclePolicy.maxConfRatio.toString()).to.equal('20000000');
+      expect('spot' in config.oraclePolicy.priceMode).to.equal(true);
+      expect(config.oraclePolicy.allowPrimaryOnly).to.equal(false);
+
+      try {
+        await program.methods.setOraclePolicy(new anchor.BN(0), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), false).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid oracle policy');
+      }
+
+      try {
+        await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN(0), { spot: {} }, new anchor.BN('50000000'), false).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid oracle policy');
+      }
+
+      await program.methods.setOraclePolicy(new anchor.BN(30), new anchor.BN('10000000'), { worst: {} }, new anchor.BN('50000000'), false).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      config = await program.account.config.fetch(configPda);
+      expect(config.oraclePolicy.maxAge.toString()).to.equal('30');
+      expect(config.oraclePolicy.maxConfRatio.toString()).to.equal('10000000');
+      expect('worst' in config.oraclePolicy.priceMode).to.equal(true);
+      expect(config.oraclePolicy.maxDivergence.toString()).to.equal('50000000');
+
+      // The local validator has no attester, so buys run on the primary feed alone unless a test posts one
+      await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), true).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      config = await program.account.config.fetch(configPda);
+      expect(config.oraclePolicy.allowPrimaryOnly).to.equal(true);
+    });
+
     it('should be able to init', async () => {
       const accounts = { payer: payer.publicKey };
       await program.methods.init().accounts(accounts).signers([payer]).rpc();
@@ -157,6 +265,96 @@ describe("Presale", () => {
       }
     });
     
+    it('should not be able to add admin if not owner', async () => {
+      const payer = await generateKeypair();
+      try {
+        await program.methods.addAdmin(payer.publicKey, ROLE_PRESALE_MANAGER).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to add and remove admin', async () => {
+      const admin = await generateKeypair();
+      let [configPda,] = anchor.web3.PublicKey.findProgramAddressSync([CONFIG_TAG], program.programId);
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      const min = new anchor.BN(1000000000);
+
+      await program.methods.addAdmin(admin.publicKey, ROLE_PRESALE_MANAGER).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      let config = await program.account.config.fetch(configPda);
+      expect(config.admins.map(entry => entry.key.toString())).to.include(admin.publicKey.toString());
+
+      await program.methods.setPresaleMinBuy(min).accounts({ payer: admin.publicKey, presale: presalePda }).signers([admin]).rpc();
+
+      await program.methods.removeAdmin(admin.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      config = await program.account.config.fetch(configPda);
+      expect(config.admins.length).to.equal(0);
+
+      try {
+        await program.methods.setPresaleMinBuy(min).accounts({ payer: admin.publicKey, presale: presalePda }).signers([admin]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should require the matching role for each operation', async () => {
+      const admin = await generateKeypair();
+      let [configPda,] = anchor.web3.PublicKey.findProgramAddressSync([CONFIG_TAG], program.programId);
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      const min = new anchor.BN(1000000000);
+
+      await program.methods.addAdmin(admin.publicKey, ROLE_ITERATION_MANAGER).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      try {
+        await program.methods.setPresaleMinBuy(min).accounts({ payer: admin.publicKey, presale: presalePda }).signers([admin]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+
+      await program.methods.grantRoles(admin.publicKey, ROLE_PRESALE_MANAGER).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      let config = await program.account.config.fetch(configPda);
+      let entry = config.admins.find(entry => entry.key.equals(admin.publicKey));
+      expect(entry.roles).to.equal(ROLE_ITERATION_MANAGER | ROLE_PRESALE_MANAGER);
+      await program.methods.setPresaleMinBuy(min).accounts({ payer: admin.publicKey, presale: presalePda }).signers([admin]).rpc();
+
+      await program.methods.revokeRoles(admin.publicKey, ROLE_PRESALE_MANAGER).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      config = await program.account.config.fetch(configPda);
+      entry = config.admins.find(entry => entry.key.equals(admin.publicKey));
+      expect(entry.roles).to.equal(ROLE_ITERATION_MANAGER);
+
+      await program.methods.removeAdmin(admin.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+    });
+
+    it('should be able to transfer ownership in two steps', async () => {
+      const newOwner = await generateKeypair();
+      let [configPda,] = anchor.web3.PublicKey.findProgramAddressSync([CONFIG_TAG], program.programId);
+
+      await program.methods.transferOwnership(newOwner.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      let config = await program.account.config.fetch(configPda);
+      expect(config.owner.toString()).to.equal(payer.publicKey.toString());
+      expect(config.pendingOwner.toString()).to.equal(newOwner.publicKey.toString());
+
+      try {
+        const stranger = await generateKeypair();
+        await program.methods.acceptOwnership().accounts({ payer: stranger.publicKey }).signers([stranger]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+
+      await program.methods.acceptOwnership().accounts({ payer: newOwner.publicKey }).signers([newOwner]).rpc();
+      config = await program.account.config.fetch(configPda);
+      expect(config.owner.toString()).to.equal(newOwner.publicKey.toString());
+
+      // Hand ownership back to the main payer for the rest of the suite
+      await program.methods.transferOwnership(payer.publicKey).accounts({ payer: newOwner.publicKey }).signers([newOwner]).rpc();
+      await program.methods.acceptOwnership().accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      config = await program.account.config.fetch(configPda);
+      expect(config.owner.toString()).to.equal(payer.publicKey.toString());
+    });
+
     it('should be able to set new Caps', async () => {
       const payer = anchor.web3.Keypair.fromSecretKey(Buffer.from(LocalAccountPrivateKeyBase58));
       let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
@@ -409,6 +607,23 @@ describe("Presale", () => {
       }
     });
 
+    it('should be able to set new adviser without its own interest', async () => {
+      const accounts = { payer: payer.publicKey };
+
+      // Rewards fall back to the presale interest
+      await program.methods
+        .initAdviser(bob_adviser_code, new anchor.BN(0), new anchor.BN(0))
+        .accounts(accounts)
+        .signers([payer])
+        .rpc();
+
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from(bob_adviser_code)
+      ], program.programId);
+      const adviser = await program.account.adviser.fetch(adviserPda);
+      expect(adviser.enabled).to.equal(true);
+    });
+
     it('should not be able to disable if Unauthorized Signer', async () => {
       const payer = await generateKeypair();
       let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
@@ -550,6 +765,164 @@ describe("Presale", () => {
       expect('open' in iteration2.status).to.equal(true);
     });
 
+    it('should not be able to pause presale if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      try {
+        await program.methods.pausePresale().accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should not be able to buy while presale or buys are paused', async () => {
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), payer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from(joe_adviser_code)
+      ], program.programId);
+
+      const buySol = () => program.methods
+        .buySol(joe_adviser_code, new anchor.BN(500000000))
+        .accounts({
+          payer: payer.publicKey,
+          iteration: iteration2Pda,
+          presale: presalePda,
+          storeInfo: store,
+          priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+          priceAttestation: null,
+          escrow: null,
+          contribution: null,
+          nextIteration: null,
+          buyer: userPda,
+          adviser: adviserPda,
+        })
+        .signers([payer])
+        .rpc();
+
+      await program.methods.pausePresale().accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      let presale = await program.account.presale.fetch(presalePda);
+      expect('paused' in presale.status).to.equal(true);
+      try {
+        await buySol();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Buys are paused');
+      }
+
+      try {
+        await program.methods.openPresale().accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Presale is paused');
+      }
+
+      await program.methods.unpausePresale().accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      presale = await program.account.presale.fetch(presalePda);
+      expect('open' in presale.status).to.equal(true);
+
+      await program.methods.pauseSubsystems(PAUSE_BUYS | PAUSE_ADVISER_CLAIMS).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      presale = await program.account.presale.fetch(presalePda);
+      expect(presale.pauseFlags).to.equal(PAUSE_BUYS | PAUSE_ADVISER_CLAIMS);
+      try {
+        await buySol();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Buys are paused');
+      }
+
+      await program.methods.unpauseSubsystems(PAUSE_BUYS | PAUSE_ADVISER_CLAIMS).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      presale = await program.account.presale.fetch(presalePda);
+      expect(presale.pauseFlags).to.equal(0);
+    });
+
+    it('should not be able to set iteration schedule if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      try {
+        await program.methods
+          .setIterationSchedule(new anchor.BN(0), new anchor.BN(0))
+          .accounts({ payer: payer.publicKey, iteration: iteration2Pda })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should enforce iteration schedule on buys', async () => {
+      const now = Math.floor(new Date().getTime() / 1000);
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), payer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from(joe_adviser_code)
+      ], program.programId);
+
+      const setSchedule = (start: number, end: number) => program.methods
+        .setIterationSchedule(new anchor.BN(start), new anchor.BN(end))
+        .accounts({ payer: payer.publicKey, iteration: iteration2Pda })
+        .signers([payer])
+        .rpc();
+
+      const buySol = () => program.methods
+        .buySol(joe_adviser_code, new anchor.BN(500000000))
+        .accounts({
+          payer: payer.publicKey,
+          iteration: iteration2Pda,
+          presale: presalePda,
+          storeInfo: store,
+          priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+          priceAttestation: null,
+          escrow: null,
+          contribution: null,
+          nextIteration: null,
+          buyer: userPda,
+          adviser: adviserPda,
+        })
+        .signers([payer])
+        .rpc();
+
+      try {
+        await setSchedule(now + 600, now);
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid iteration schedule');
+      }
+
+      await setSchedule(now + 600, 0);
+      const iteration2 = await program.account.iteration.fetch(iteration2Pda);
+      expect(iteration2.startTs.toString()).to.equal((now + 600).toString());
+      try {
+        await buySol();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Iteration not started');
+      }
+
+      await setSchedule(now - 600, now - 300);
+      try {
+        await buySol();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Iteration already closed');
+      }
+
+      await setSchedule(0, 0);
+    });
+
     it('should be able to deposit_sol to iteration with joe adviser', async () => {
       let iteration2id = 2;
 
@@ -575,6 +948,10 @@ describe("Presale", () => {
         presale: presalePda,
         storeInfo: store,
         priceUpdate: priceUpdate,
+        priceAttestation: null,
+        escrow: null,
+        contribution: null,
+        nextIteration: null,
         buyer: userPda,
         adviser: adviserPda,
       };
@@ -601,7 +978,7 @@ describe("Presale", () => {
 
       expect('open' in iteration2.status).to.equal(true);
 
-      expect(tokenAmount.toString()).to.equal(presale.totalReleased.toString());
+      expect(tokenAmount.add(adviser.tokenReward).toString()).to.equal(presale.totalReleased.toString());
       expect(tokenAmount.toString()).to.equal(iteration2.sold.toString());
       expect(tokenAmount.toString()).to.equal(buyer.balance.toString());
       
@@ -611,7 +988,83 @@ describe("Presale", () => {
       expect(adviserBalance).to.be.greaterThanOrEqual(adviser.solReward.toNumber());
     });
 
-    it('should be able to deposit_usdc to iteration with bob adviser', async () => {
+    it('should not be able to add accepted mint if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      try {
+        await program.methods
+          .addAcceptedMint({ peg: {} }, new Array(32).fill(0))
+          .accounts({ payer: payer.publicKey, mint: stables.usdc.mint })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to add accepted mints', async () => {
+      for (const mint of [stables.usdc.mint, stables.usdt.mint]) {
+        await program.methods
+          .addAcceptedMint({ peg: {} }, new Array(32).fill(0))
+          .accounts({ payer: payer.publicKey, mint: mint })
+          .signers([payer])
+          .rpc();
+
+        let [acceptedMintPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+          MINT_TAG, Buffer.from('_'), mint.toBuffer()
+        ], program.programId);
+        const acceptedMint = await program.account.acceptedMint.fetch(acceptedMintPda);
+        expect(acceptedMint.mint.toString()).to.equal(mint.toString());
+        expect(acceptedMint.decimals).to.equal(6);
+        expect('peg' in acceptedMint.priceSource).to.equal(true);
+        expect(acceptedMint.enabled).to.equal(true);
+      }
+    });
+
+    it('should not be able to set depeg policy if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      let [acceptedMintPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        MINT_TAG, Buffer.from('_'), stables.usdt.mint.toBuffer()
+      ], program.programId);
+      try {
+        await program.methods
+          .setAcceptedMintDepegPolicy(new anchor.BN('990000000'), { reject: {} })
+          .accounts({ payer: payer.publicKey, acceptedMint: acceptedMintPda })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to set depeg policy', async () => {
+      let [acceptedMintPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        MINT_TAG, Buffer.from('_'), stables.usdt.mint.toBuffer()
+      ], program.programId);
+
+      try {
+        await program.methods
+          .setAcceptedMintDepegPolicy(new anchor.BN('1000000001'), { reject: {} })
+          .accounts({ payer: payer.publicKey, acceptedMint: acceptedMintPda })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid depeg threshold');
+      }
+
+      await program.methods
+        .setAcceptedMintDepegPolicy(new anchor.BN('990000000'), { reprice: {} })
+        .accounts({ payer: payer.publicKey, acceptedMint: acceptedMintPda })
+        .signers([payer])
+        .rpc();
+      const acceptedMint = await program.account.acceptedMint.fetch(acceptedMintPda);
+      expect(acceptedMint.depegThreshold.toString()).to.equal('990000000');
+      expect('reprice' in acceptedMint.depegPolicy).to.equal(true);
+    });
+
+    it('should be able to buy_with_token usdc to iteration with bob adviser', async () => {
       let iteration2id = 2;
 
       let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([
@@ -639,13 +1092,20 @@ describe("Presale", () => {
 
       const amount = new anchor.BN(50000000); // $50
       await program.methods
-        .buyUsdc(bob_adviser_code, amount)
+        .buyWithToken(bob_adviser_code, amount)
         .accounts({
           payer: payer.publicKey,
           iteration: iteration2Pda,
           presale: presalePda,
           buyer: userPda,
           adviser: adviserPda,
+          mint: stables.usdc.mint,
+          priceUpdate: null,
+          priceAttestation: null,
+          escrow: null,
+          escrowAta: null,
+          contribution: null,
+          nextIteration: null,
           buyerAta: stables.usdc.payerAta,
           storeAta: stables.usdc.storeAta,
           adviserPdaAta: adviserPdaAta.address,
@@ -663,11 +1123,15 @@ describe("Presale", () => {
 
       const tokenAmount = amount.mul(precision).mul(precision).div(iteration2.price).div(stablePresicion);
 
-      expect(adviser.usdcReward.toString()).to.equal((amount.mul(firstRew).div(precision)).toString());
+      let [rewardPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REWARD_TAG, Buffer.from('_'), Buffer.from(bob_adviser_code), Buffer.from('_'), stables.usdc.mint.toBuffer()
+      ], program.programId);
+      const reward = await program.account.adviserReward.fetch(rewardPda);
+      expect(reward.amount.toString()).to.equal((amount.mul(firstRew).div(precision)).toString());
       expect(adviser.tokenReward.toString()).to.equal((tokenAmount.mul(secondRew).div(precision)).toString());
     });
 
-    it('should be able to deposit_usdt to iteration with bob adviser', async () => {
+    it('should be able to buy_with_token usdt to iteration with bob adviser', async () => {
       let iteration2id = 2;
 
       let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([
@@ -695,13 +1159,20 @@ describe("Presale", () => {
 
       const amount = new anchor.BN(50000000); // $50
       await program.methods
-        .buyUsdt(bob_adviser_code, amount)
+        .buyWithToken(bob_adviser_code, amount)
         .accounts({
           payer: payer.publicKey,
           iteration: iteration2Pda,
           presale: presalePda,
           buyer: userPda,
           adviser: adviserPda,
+          mint: stables.usdt.mint,
+          priceUpdate: null,
+          priceAttestation: null,
+          escrow: null,
+          escrowAta: null,
+          contribution: null,
+          nextIteration: null,
           buyerAta: stables.usdt.payerAta,
           storeAta: stables.usdt.storeAta,
           adviserPdaAta: adviserPdaAta.address,
@@ -719,16 +1190,94 @@ describe("Presale", () => {
 
       const tokenAmount = amount.mul(precision).mul(precision).div(iteration2.price).div(stablePresicion);
 
-      expect(adviser.usdtReward.toString()).to.equal((amount.mul(firstRew).div(precision)).toString());
+      let [rewardPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REWARD_TAG, Buffer.from('_'), Buffer.from(bob_adviser_code), Buffer.from('_'), stables.usdt.mint.toBuffer()
+      ], program.programId);
+      const reward = await program.account.adviserReward.fetch(rewardPda);
+      expect(reward.amount.toString()).to.equal((amount.mul(firstRew).div(precision)).toString());
       expect(adviser.tokenReward.toString()).to.equal((tokenAmount.mul(secondRew).div(precision).mul(new anchor.BN(2))).toString());
     });
 
-    it('should be able to deposit_sol to iteration', async () => {
-      let iteration2id = 2;
-      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([
-      ], program.programId);
-
-      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+    it('should not be able to post price attestation if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      try {
+        await program.methods
+          .postPriceAttestation(Array.from(SOL_USD_FEED_ID), new anchor.BN('14400000000'), 8)
+          .accounts({ payer: payer.publicKey })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should not be able to deposit_sol when price sources diverge', async () => {
+      const attester = await generateKeypair();
+      await program.methods.addAdmin(attester.publicKey, ROLE_PRICE_ATTESTER).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+
+      await program.methods
+        .postPriceAttestation(Array.from(SOL_USD_FEED_ID), new anchor.BN('10000000000'), 8)
+        .accounts({ payer: attester.publicKey })
+        .signers([attester])
+        .rpc();
+
+      let [attestationPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ATTESTATION_TAG, Buffer.from('_'), SOL_USD_FEED_ID
+      ], program.programId);
+      const attestation = await program.account.priceAttestation.fetch(attestationPda);
+      expect(attestation.price.toString()).to.equal('10000000000');
+      expect(attestation.expo).to.equal(8);
+
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), bob_adviser.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      const expectError = async (priceAttestation: PublicKey | null, message: string) => {
+        try {
+          await program.methods
+            .buySol("", new anchor.BN(1000000000))
+            .accounts({
+              payer: bob_adviser.publicKey,
+              iteration: iteration2Pda,
+              presale: presalePda,
+              storeInfo: store,
+              priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+              priceAttestation: priceAttestation,
+              nextIteration: null,
+              buyer: userPda,
+              adviser: adviserPda,
+            })
+            .signers([bob_adviser])
+            .rpc();
+          expect.fail('Expected action to throw an error');
+        } catch (err) {
+          expect(err.error.errorMessage).to.equal(message);
+        }
+      };
+
+      // Without the opt-out the primary price is never taken unchecked
+      await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), false).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      await expectError(null, 'Fresh price attestation required');
+      await expectError(attestationPda, 'Oracle price sources diverge');
+      await program.methods.setOraclePolicy(new anchor.BN(60), new anchor.BN('20000000'), { spot: {} }, new anchor.BN('50000000'), true).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+
+      await program.methods.removeAdmin(attester.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+    });
+
+    it('should be able to deposit_sol to iteration', async () => {
+      let iteration2id = 2;
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([
+      ], program.programId);
+
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
         ROUND_TAG, Buffer.from('_'), i16ToBytesLE(iteration2id)
       ], program.programId);
 
@@ -748,6 +1297,10 @@ describe("Presale", () => {
         presale: presalePda,
         storeInfo: store,
         priceUpdate: priceUpdate,
+        priceAttestation: null,
+        escrow: null,
+        contribution: null,
+        nextIteration: null,
         buyer: userPda,
         adviser: adviserPda,
       };
@@ -767,8 +1320,125 @@ describe("Presale", () => {
         const tokenAmount = usd.mul(precision).mul(precision).div(iteration2.price);
   
         expect(tokenAmount.toString()).to.equal(buyer.balance.toString());        
+        expect(buyer.version).to.equal(3);
+        expect(buyer.purchases).to.equal(1);
+        expect(buyer.paid[0].mint.toString()).to.equal(PublicKey.default.toString());
+        expect(buyer.paid[0].amount.toString()).to.equal(amount.toString());
+        expect(buyer.usdAmount.toString()).to.equal(usd.mul(precision).toString());
+        expect(buyer.iterations[0].iteration).to.equal(iteration2id);
+        expect(buyer.iterations[0].amount.toString()).to.equal(tokenAmount.toString());
+    });
+
+    it('should not be able to migrate a current buyer', async () => {
+      try {
+        await program.methods.migrateBuyer().accounts({ payer: payer.publicKey, wallet: bob_adviser.publicKey }).signers([payer]).rpc();
+        expect.fail('should have failed');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Buyer already migrated');
+      }
     });
     
+    it('should be able to migrate a version 0 buyer', async () => {
+      // Matches the fixture loaded into the validator, see tests/fixtures/legacy_buyer.json
+      const legacyWallet = anchor.web3.Keypair.fromSeed(new Uint8Array(32).fill(19));
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), legacyWallet.publicKey.toBuffer()
+      ], program.programId);
+
+      const legacyInfo = await program.provider.connection.getAccountInfo(userPda);
+      expect(legacyInfo.data.length).to.equal(8 + 16 + 1);
+
+      await program.methods.migrateBuyer().accounts({ payer: payer.publicKey, wallet: legacyWallet.publicKey }).signers([payer]).rpc();
+
+      const buyer = await program.account.buyer.fetch(userPda);
+      const precision = new anchor.BN(1000000000);
+      expect(buyer.balance.toString()).to.equal(new anchor.BN(500).mul(precision).mul(precision).toString());
+      expect(buyer.claimed.toString()).to.equal('0');
+      expect(buyer.quoteNonce.toString()).to.equal('0');
+      expect(buyer.version).to.equal(3);
+      expect(buyer.purchases).to.equal(0);
+      expect(buyer.paid.length).to.equal(0);
+      expect(buyer.usdPending).to.equal(true);
+    });
+
+    it('should be able to buy_sol_with_quote within the oracle band', async () => {
+      const quoteBuyer = await generateKeypair();
+      const expiry = Math.floor(new Date().getTime() / 1000) + 600; // 10m from now
+
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), quoteBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      const accounts = {
+        payer: quoteBuyer.publicKey,
+        iteration: iteration2Pda,
+        presale: presalePda,
+        storeInfo: store,
+        priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+        priceAttestation: null,
+        escrow: null,
+        contribution: null,
+        nextIteration: null,
+        buyer: userPda,
+        adviser: adviserPda,
+      };
+
+      const buyWithQuote = async (price: anchor.BN, nonce: number) => {
+        const message = Uint8Array.from(Buffer.concat([
+          Buffer.from('presale:quote:'),
+          price.toArrayLike(Buffer, 'le', 8),
+          new anchor.BN(expiry).toTwos(64).toArrayLike(Buffer, 'le', 8),
+          quoteBuyer.publicKey.toBuffer(),
+          new anchor.BN(nonce).toArrayLike(Buffer, 'le', 8),
+        ]));
+        const signature: Uint8Array = await ed.sign(message, payer.secretKey.slice(0, 32));
+
+        await program.methods
+          .buySolWithQuote("", new anchor.BN(1000000000), price, new anchor.BN(expiry), new anchor.BN(nonce), Array.from(signature), 0)
+          .accounts(accounts)
+          .preInstructions([
+            anchor.web3.Ed25519Program.createInstructionWithPublicKey({
+              publicKey: payer.publicKey.toBytes(),
+              message: message,
+              signature: signature,
+            })
+          ])
+          .signers([quoteBuyer])
+          .rpc();
+      };
+
+      const quotePrice = new anchor.BN('145000000000'); // $145, within 1% of the oracle
+      await buyWithQuote(quotePrice, 0);
+
+      const iteration2 = await program.account.iteration.fetch(iteration2Pda);
+      const buyer = await program.account.buyer.fetch(userPda);
+      const precision = new anchor.BN(1000000000);
+      const tokenAmount = quotePrice.mul(precision).div(iteration2.price);
+      expect(buyer.balance.toString()).to.equal(tokenAmount.toString());
+      expect(buyer.quoteNonce.toString()).to.equal('1');
+
+      try {
+        await buyWithQuote(quotePrice, 0);
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Quote nonce already used');
+      }
+
+      try {
+        await buyWithQuote(new anchor.BN('150000000000'), 1);
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Quote price is outside of the oracle band');
+      }
+    });
+
     it('should not be able to claim_sol adviser interest with invalid sign', async () => {
       const payer = anchor.web3.Keypair.fromSecretKey(Buffer.from(LocalAccountPrivateKeyBase58));
       const deadline = Math.floor(new Date().getTime() / 1000) - 50; // 10m from now
@@ -792,7 +1462,7 @@ describe("Presale", () => {
         .add(
           await program.methods
             .claimSol(joe_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
-            .accounts({ payer: joe_adviser.publicKey, adviser: adviserPda })
+            .accounts({ payer: joe_adviser.publicKey, adviser: adviserPda, escrow: null })
             .signers([joe_adviser])
             .instruction()
         );
@@ -838,7 +1508,7 @@ describe("Presale", () => {
         .add(
           await program.methods
             .claimSol(joe_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
-            .accounts({ payer: joe_adviser.publicKey, adviser: adviserPda })
+            .accounts({ payer: joe_adviser.publicKey, adviser: adviserPda, escrow: null })
             .instruction()
         );
 
@@ -860,7 +1530,7 @@ describe("Presale", () => {
       expect((adviserBalance2 - adviserBalance1) / 1000000000).to.approximately(75000000 / 1000000000, 0.0001);
     });
 
-    it('should be able to claim_usdc adviser interest', async () => {
+    it('should be able to claim_reward usdc adviser interest', async () => {
       const deadline = Math.floor(new Date().getTime() / 1000) + 600; // 10m from now
 
       const message = Uint8Array.from(Buffer.from(`${bob_adviser_code}${bob_adviser.publicKey}${deadline}`));
@@ -897,12 +1567,15 @@ describe("Presale", () => {
         )
         .add(
           await program.methods
-            .claimUsdc(bob_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
+            .claimReward(bob_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
             .accounts({ 
               payer: bob_adviser.publicKey,
               adviser: adviserPda,
+              mint: stables.usdc.mint,
               adviserAta: adviserAta.address,
               adviserPdaAta: adviserPdaAta.address,
+              escrow: null,
+              escrowAta: null,
             })
             .instruction()
         );
@@ -919,13 +1592,16 @@ describe("Presale", () => {
         throw confirmation.value.err;
       }
       
-      const adviser = await program.account.adviser.fetch(adviserPda);
+      let [rewardPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REWARD_TAG, Buffer.from('_'), Buffer.from(bob_adviser_code), Buffer.from('_'), stables.usdc.mint.toBuffer()
+      ], program.programId);
+      const reward = await program.account.adviserReward.fetch(rewardPda);
       const usdcAccount = await getAccount(provider.connection, adviserAta.address);
       expect(usdcAccount.amount.toString()).to.equal('5000000');
-      expect(adviser.usdcReward.toString()).to.equal('0');
+      expect(reward.amount.toString()).to.equal('0');
     });
 
-    it('should be able to claim_usdt adviser interest', async () => {
+    it('should be able to claim_reward usdt adviser interest', async () => {
       const deadline = Math.floor(new Date().getTime() / 1000) + 600; // 10m from now
 
       const message = Uint8Array.from(Buffer.from(`${bob_adviser_code}${bob_adviser.publicKey}${deadline}`));
@@ -962,12 +1638,15 @@ describe("Presale", () => {
         )
         .add(
           await program.methods
-            .claimUsdt(bob_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
+            .claimReward(bob_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
             .accounts({ 
               payer: bob_adviser.publicKey,
               adviser: adviserPda,
+              mint: stables.usdt.mint,
               adviserAta: adviserAta.address,
               adviserPdaAta: adviserPdaAta.address,
+              escrow: null,
+              escrowAta: null,
             })
             .instruction()
         );
@@ -984,10 +1663,13 @@ describe("Presale", () => {
         throw confirmation.value.err;
       }
       
-      const adviser = await program.account.adviser.fetch(adviserPda);
+      let [rewardPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REWARD_TAG, Buffer.from('_'), Buffer.from(bob_adviser_code), Buffer.from('_'), stables.usdt.mint.toBuffer()
+      ], program.programId);
+      const reward = await program.account.adviserReward.fetch(rewardPda);
       const usdtAccount = await getAccount(provider.connection, adviserAta.address);
       expect(usdtAccount.amount.toString()).to.equal('5000000');
-      expect(adviser.usdtReward.toString()).to.equal('0');
+      expect(reward.amount.toString()).to.equal('0');
     });
     
     it('should not be able to claim_sol bob adviser interest by joe signer', async () => {
@@ -1012,7 +1694,7 @@ describe("Presale", () => {
         .add(
           await program.methods
             .claimSol(bob_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
-            .accounts({ payer: bob_adviser.publicKey, adviser: adviserPda })
+            .accounts({ payer: bob_adviser.publicKey, adviser: adviserPda, escrow: null })
             .signers([joe_adviser])
             .instruction()
         );
@@ -1032,6 +1714,957 @@ describe("Presale", () => {
       }
     });
     
+    it('should not be able to set iteration next if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      try {
+        await program.methods
+          .setIterationNext(3)
+          .accounts({ payer: payer.publicKey, iteration: iteration2Pda })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should fill a buy across chained iterations and open the next one', async () => {
+      const chainBuyer = await generateKeypair();
+      const iteration3id = 3;
+      const iteration4id = 4;
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration2Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(2)
+      ], program.programId);
+      let [iteration3Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(iteration3id)
+      ], program.programId);
+      let [iteration4Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(iteration4id)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), chainBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      // 100 tokens at $1, then $2 per token
+      await program.methods.createIteration(iteration3id, new anchor.BN('1000000000'), new anchor.BN('100000000000')).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      await program.methods.createIteration(iteration4id, new anchor.BN('2000000000'), new anchor.BN('1000000000000')).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      await program.methods.setIterationNext(iteration4id).accounts({ payer: payer.publicKey, iteration: iteration3Pda }).signers([payer]).rpc();
+      await program.methods.closeIteration().accounts({ payer: payer.publicKey, iteration: iteration2Pda, presale: presalePda }).signers([payer]).rpc();
+      await program.methods.openIteration().accounts({ payer: payer.publicKey, iteration: iteration3Pda, presale: presalePda }).signers([payer]).rpc();
+
+      await program.methods
+        .buySol("", new anchor.BN(1000000000))
+        .accounts({
+          payer: chainBuyer.publicKey,
+          iteration: iteration3Pda,
+          nextIteration: iteration4Pda,
+          presale: presalePda,
+          storeInfo: store,
+          priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+          priceAttestation: null,
+          escrow: null,
+          contribution: null,
+          buyer: userPda,
+          adviser: adviserPda,
+        })
+        .signers([chainBuyer])
+        .rpc();
+
+      // $144: $100 buys out iteration 3, the remaining $44 buys 22 tokens of iteration 4
+      const presale = await program.account.presale.fetch(presalePda);
+      const iteration3 = await program.account.iteration.fetch(iteration3Pda);
+      const iteration4 = await program.account.iteration.fetch(iteration4Pda);
+      const buyer = await program.account.buyer.fetch(userPda);
+      expect(iteration3.sold.toString()).to.equal('100000000000');
+      expect('closed' in iteration3.status).to.equal(true);
+      expect(iteration4.sold.toString()).to.equal('22000000000');
+      expect('open' in iteration4.status).to.equal(true);
+      expect(presale.iteration).to.equal(iteration4id);
+      expect(buyer.balance.toString()).to.equal('122000000000');
+    });
+
+    it('should be able to advance an ended iteration and roll its unsold supply over', async () => {
+      const now = Math.floor(new Date().getTime() / 1000);
+      const cranker = await generateKeypair();
+      const iteration5id = 5;
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration4Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(4)
+      ], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(iteration5id)
+      ], program.programId);
+
+      await program.methods.createIteration(iteration5id, new anchor.BN('3000000000'), new anchor.BN('1000000000000')).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      await program.methods.setIterationNext(iteration5id).accounts({ payer: payer.publicKey, iteration: iteration4Pda }).signers([payer]).rpc();
+
+      const advance = () => program.methods
+        .advanceIteration()
+        .accounts({ payer: cranker.publicKey, iteration: iteration4Pda, nextIteration: iteration5Pda })
+        .signers([cranker])
+        .rpc();
+
+      try {
+        await advance();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Iteration neither sold out nor ended');
+      }
+
+      await program.methods.setIterationSchedule(new anchor.BN(now - 600), new anchor.BN(now - 300)).accounts({ payer: payer.publicKey, iteration: iteration4Pda }).signers([payer]).rpc();
+      await program.methods.setPresaleLeftoverPolicy({ rollover: {} }).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+      await advance();
+
+      const presale = await program.account.presale.fetch(presalePda);
+      const iteration4 = await program.account.iteration.fetch(iteration4Pda);
+      const iteration5 = await program.account.iteration.fetch(iteration5Pda);
+      const unsold = iteration4.total.sub(iteration4.sold);
+      expect('closed' in iteration4.status).to.equal(true);
+      expect('open' in iteration5.status).to.equal(true);
+      expect(iteration5.total.toString()).to.equal(new anchor.BN('1000000000000').add(unsold).toString());
+      expect(presale.iteration).to.equal(iteration5id);
+    });
+
+    it('should not be able to buy above the presale hard cap', async () => {
+      const capBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), capBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      let presale = await program.account.presale.fetch(presalePda);
+      try {
+        await program.methods.setPresaleHardCap(presale.totalReleased.subn(1)).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Hard cap is below the sold supply');
+      }
+
+      await program.methods.setPresaleHardCap(presale.totalReleased).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+      presale = await program.account.presale.fetch(presalePda);
+      expect(presale.hardCap.toString()).to.equal(presale.totalReleased.toString());
+
+      try {
+        await program.methods
+          .buySol("", new anchor.BN(1000000000))
+          .accounts({
+            payer: capBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([capBuyer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Presale hard cap exceeded');
+      }
+
+      await program.methods.setPresaleHardCap(new anchor.BN(0)).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+    });
+
+    it('should enforce presale, iteration and wallet buy limits', async () => {
+      const limitBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), limitBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      // 1 SOL is $144 with the mock oracle
+      const buy = async () => {
+        await program.methods
+          .buySol("", new anchor.BN(1000000000))
+          .accounts({
+            payer: limitBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([limitBuyer])
+          .rpc();
+      };
+      const expectError = async (message: string) => {
+        try {
+          await buy();
+          expect.fail('Expected action to throw an error');
+        } catch (err) {
+          expect(err.error.errorMessage).to.equal(message);
+        }
+      };
+
+      await program.methods.setPresaleBuyLimits(new anchor.BN('100000000000'), new anchor.BN('100000000000')).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+      await expectError('Presale transaction limit exceeded');
+
+      await program.methods.setIterationBuyLimits(new anchor.BN(0), new anchor.BN('200000000000')).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+      await expectError('Wallet limit exceeded');
+
+      await program.methods.setIterationBuyLimits(new anchor.BN('500000000000'), new anchor.BN(0)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+      await expectError('Iteration min buy not reached');
+
+      await program.methods.setIterationBuyLimits(new anchor.BN(0), new anchor.BN('100000000000')).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+      await expectError('Iteration transaction limit exceeded');
+
+      await program.methods.setIterationBuyLimits(new anchor.BN(0), new anchor.BN(0)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+      await program.methods.setPresaleBuyLimits(new anchor.BN(0), new anchor.BN(0)).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+    });
+
+    it('should require a backfilled usd amount from migrated buyers under wallet limits', async () => {
+      // Migrated in 'should be able to migrate a version 0 buyer'
+      const legacyWallet = anchor.web3.Keypair.fromSeed(new Uint8Array(32).fill(19));
+      await fund(legacyWallet.publicKey);
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), legacyWallet.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      const expectError = async (message: string) => {
+        try {
+          await program.methods
+            .buySol("", new anchor.BN(1000000000))
+            .accounts({
+              payer: legacyWallet.publicKey,
+              iteration: iteration5Pda,
+              nextIteration: null,
+              presale: presalePda,
+              storeInfo: store,
+              priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+              priceAttestation: null,
+              escrow: null,
+              contribution: null,
+              buyer: userPda,
+              adviser: adviserPda,
+            })
+            .signers([legacyWallet])
+            .rpc();
+          expect.fail('Expected action to throw an error');
+        } catch (err) {
+          expect(err.error.errorMessage).to.equal(message);
+        }
+      };
+
+      await program.methods.setPresaleBuyLimits(new anchor.BN('1000000000000'), new anchor.BN(0)).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+      await expectError('Buyer USD amount is not backfilled');
+
+      // $900 spent before the ledger, so a $144 buy goes over the $1000 limit
+      await program.methods.backfillBuyerUsd(legacyWallet.publicKey, new anchor.BN('900000000000')).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      const buyer = await program.account.buyer.fetch(userPda);
+      expect(buyer.usdPending).to.equal(false);
+      expect(buyer.usdAmount.toString()).to.equal('900000000000');
+      await expectError('Wallet limit exceeded');
+
+      try {
+        await program.methods.backfillBuyerUsd(legacyWallet.publicKey, new anchor.BN(0)).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Buyer USD amount is already backfilled');
+      }
+
+      await program.methods.setPresaleBuyLimits(new anchor.BN(0), new anchor.BN(0)).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+    });
+
+    it('should not be able to buy into a gated iteration without a registered allocation', async () => {
+      const privateBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), privateBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      const merkleRoot = Array.from(anchor.web3.Keypair.generate().publicKey.toBytes());
+      await program.methods.setIterationAllowlist(merkleRoot).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+
+      try {
+        await program.methods
+          .buySol("", new anchor.BN(1000000000))
+          .accounts({
+            payer: privateBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([privateBuyer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Wallet not allowlisted');
+      }
+
+      try {
+        await program.methods
+          .registerAllocation(5, new anchor.BN('1000000000000'), [merkleRoot])
+          .accounts({ payer: privateBuyer.publicKey })
+          .signers([privateBuyer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid allowlist proof');
+      }
+
+      await program.methods.setIterationAllowlist(Array(32).fill(0)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+    });
+
+    it('should be able to buy into a gated iteration within the registered allocation', async () => {
+      const privateBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), privateBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      // 1 SOL is $144 with the mock oracle, the allocation fits one and a half of those
+      const iteration5 = await program.account.iteration.fetch(iteration5Pda);
+      const precision = new anchor.BN(1000000000);
+      const tokenAmount = new anchor.BN(144).mul(precision).mul(precision).div(iteration5.price);
+      const allocation = tokenAmount.add(tokenAmount.divn(2));
+
+      // Two leaf tree, each leaf commits a wallet to its allocation
+      const getLeaf = (wallet: PublicKey, amount: anchor.BN) => Buffer.from(keccak_256(Buffer.concat([wallet.toBuffer(), amount.toArrayLike(Buffer, 'le', 16)])));
+      const leaf = getLeaf(privateBuyer.publicKey, allocation);
+      const sibling = getLeaf(anchor.web3.Keypair.generate().publicKey, allocation);
+      const merkleRoot = Buffer.from(keccak_256(Buffer.concat(Buffer.compare(leaf, sibling) <= 0 ? [leaf, sibling] : [sibling, leaf])));
+      await program.methods.setIterationAllowlist(Array.from(merkleRoot)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+
+      await program.methods
+        .registerAllocation(5, allocation, [Array.from(sibling)])
+        .accounts({ payer: privateBuyer.publicKey })
+        .signers([privateBuyer])
+        .rpc();
+
+      const buy = async (amount: anchor.BN) => {
+        await program.methods
+          .buySol("", amount)
+          .accounts({
+            payer: privateBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([privateBuyer])
+          .rpc();
+      };
+
+      await buy(new anchor.BN(1000000000));
+      const buyer = await program.account.buyer.fetch(userPda);
+      expect(buyer.allocations[0].amount.toString()).to.equal(allocation.toString());
+      expect(buyer.iterations[0].amount.toString()).to.equal(tokenAmount.toString());
+
+      try {
+        await buy(new anchor.BN(600000000));
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Allocation exceeded');
+      }
+
+      await program.methods.setIterationAllowlist(Array(32).fill(0)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+    });
+
+    it('should enforce kyc tier limits until a signed pass is submitted', async () => {
+      const kycBuyer = await generateKeypair();
+      const expiry = Math.floor(new Date().getTime() / 1000) + 600; // 10m from now
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), kycBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      const buy = async () => {
+        await program.methods
+          .buySol("", new anchor.BN(1000000000))
+          .accounts({
+            payer: kycBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([kycBuyer])
+          .rpc();
+      };
+
+      const limits = [new anchor.BN('100000000000'), new anchor.BN(0), new anchor.BN(0), new anchor.BN(0)];
+      await program.methods.setKycLimits(limits).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+
+      try {
+        await buy();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('KYC tier limit exceeded');
+      }
+
+      const tier = 1;
+      const issuedAt = Math.floor(Date.now() / 1000) - 60;
+      const kycSigner = anchor.web3.Keypair.generate();
+      const submitPass = async (issuedAt: number) => {
+        const message = Uint8Array.from(Buffer.concat([
+          Buffer.from('presale:kyc:'),
+          kycBuyer.publicKey.toBuffer(),
+          Buffer.from([tier]),
+          new anchor.BN(expiry).toArrayLike(Buffer, 'le', 8),
+          new anchor.BN(issuedAt).toArrayLike(Buffer, 'le', 8),
+        ]));
+        const signature: Uint8Array = await ed.sign(message, kycSigner.secretKey.slice(0, 32));
+        await program.methods
+          .submitKycPass(tier, new anchor.BN(expiry), new anchor.BN(issuedAt), Array.from(signature), 0)
+          .accounts({ payer: kycBuyer.publicKey })
+          .preInstructions([
+            anchor.web3.Ed25519Program.createInstructionWithPublicKey({
+              publicKey: kycSigner.publicKey.toBytes(),
+              message: message,
+              signature: signature,
+            })
+          ])
+          .signers([kycBuyer])
+          .rpc();
+      };
+
+      try {
+        await submitPass(issuedAt);
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('KYC signer is not set');
+      }
+
+      await program.methods.setKycSigner(kycSigner.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      await submitPass(issuedAt);
+
+      try {
+        await submitPass(issuedAt - 1);
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('KYC pass is stale');
+      }
+
+      await buy();
+      const buyer = await program.account.buyer.fetch(userPda);
+      expect(buyer.kycTier).to.equal(tier);
+      expect(buyer.purchases).to.equal(1);
+
+      await program.methods.setKycLimits(Array(4).fill(new anchor.BN(0))).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+    });
+
+    it('should not be able to buy from a blocked wallet', async () => {
+      const blockedBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), blockedBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+      let [blockedWalletPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        BLOCKLIST_TAG, Buffer.from('_'), blockedBuyer.publicKey.toBuffer()
+      ], program.programId);
+
+      await program.methods.blockWallet(blockedBuyer.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+
+      try {
+        await program.methods
+          .buySol("", new anchor.BN(1000000000))
+          .accounts({
+            payer: blockedBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([blockedBuyer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Wallet is blocked');
+      }
+
+      await program.methods.unblockWallet(blockedBuyer.publicKey).accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+      const blockedWallet = await provider.connection.getAccountInfo(blockedWalletPda);
+      expect(blockedWallet).to.equal(null);
+    });
+
+    it('should credit bonus tokens once the buyer volume crosses a bonus threshold', async () => {
+      const bonusBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), bonusBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from("")
+      ], program.programId);
+
+      const threshold = new anchor.BN('200000000000'); // $200
+      try {
+        await program.methods.setPresaleBonus(threshold, new anchor.BN('2000000000')).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid bonus percent');
+      }
+
+      const percent = new anchor.BN(100000000); // 10%
+      await program.methods.setPresaleBonus(threshold, percent).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+
+      // 1 SOL is $144 with the mock oracle
+      const buy = async (amount: anchor.BN) => {
+        await program.methods
+          .buySol("", amount)
+          .accounts({
+            payer: bonusBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: adviserPda,
+          })
+          .signers([bonusBuyer])
+          .rpc();
+      };
+
+      const precision = new anchor.BN(1000000000);
+      const price = (await program.account.iteration.fetch(iteration5Pda)).price;
+      const bonusPrice = price.mul(precision).div(precision.add(percent));
+
+      // $144 stays below the threshold
+      await buy(new anchor.BN(1000000000));
+      const firstAmount = new anchor.BN(144).mul(precision).mul(precision).div(price);
+      let buyer = await program.account.buyer.fetch(userPda);
+      expect(buyer.balance.toString()).to.equal(firstAmount.toString());
+
+      // $72 more takes the buyer volume to $216, so the whole buy gets the bonus
+      const sold = (await program.account.iteration.fetch(iteration5Pda)).sold;
+      await buy(new anchor.BN(500000000));
+      const secondAmount = new anchor.BN(72).mul(precision).mul(precision).div(bonusPrice);
+      const iteration5 = await program.account.iteration.fetch(iteration5Pda);
+      buyer = await program.account.buyer.fetch(userPda);
+      expect(buyer.balance.toString()).to.equal(firstAmount.add(secondAmount).toString());
+      expect(iteration5.sold.sub(sold).toString()).to.equal(secondAmount.toString());
+
+      // Bonus tokens count against allowlist allocations, a single leaf tree has the leaf as root
+      const usd = new anchor.BN('57600000000'); // 0.4 SOL
+      const allocation = firstAmount.add(secondAmount).add(usd.mul(precision).div(price));
+      const leaf = keccak_256(Buffer.concat([bonusBuyer.publicKey.toBuffer(), allocation.toArrayLike(Buffer, 'le', 16)]));
+      await program.methods.setIterationAllowlist(Array.from(leaf)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+      await program.methods.registerAllocation(5, allocation, []).accounts({ payer: bonusBuyer.publicKey }).signers([bonusBuyer]).rpc();
+
+      try {
+        await buy(new anchor.BN(400000000));
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Allocation exceeded');
+      }
+
+      await program.methods.setIterationAllowlist(Array(32).fill(0)).accounts({ payer: payer.publicKey, iteration: iteration5Pda }).signers([payer]).rpc();
+      await program.methods.setPresaleBonus(threshold, new anchor.BN(0)).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+    });
+
+    it('should credit the adviser buyer discount to buys using its code', async () => {
+      const discountBuyer = await generateKeypair();
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), discountBuyer.publicKey.toBuffer()
+      ], program.programId);
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from(joe_adviser_code)
+      ], program.programId);
+
+      try {
+        await program.methods.setPresaleBuyerDiscount(new anchor.BN('2000000000')).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Invalid buyer discount');
+      }
+
+      const discount = new anchor.BN(50000000); // 5%
+      await program.methods.setAdviserBuyerDiscount(discount).accounts({ payer: payer.publicKey, adviser: adviserPda }).signers([payer]).rpc();
+
+      const buy = async (code: string) => {
+        let [codePda,] = anchor.web3.PublicKey.findProgramAddressSync([
+          REF_TAG, Buffer.from('_'), Buffer.from(code)
+        ], program.programId);
+        await program.methods
+          .buySol(code, new anchor.BN(1000000000))
+          .accounts({
+            payer: discountBuyer.publicKey,
+            iteration: iteration5Pda,
+            nextIteration: null,
+            presale: presalePda,
+            storeInfo: store,
+            priceUpdate: new PublicKey('7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE'),
+            priceAttestation: null,
+            escrow: null,
+            contribution: null,
+            buyer: userPda,
+            adviser: codePda,
+          })
+          .signers([discountBuyer])
+          .rpc();
+      };
+
+      try {
+        await buy('UNKNOWN');
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Adviser code is not active');
+      }
+
+      await program.methods.disableAdviser().accounts({ payer: payer.publicKey, adviser: adviserPda }).signers([payer]).rpc();
+      try {
+        await buy(joe_adviser_code);
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Adviser code is not active');
+      }
+      await program.methods.enableAdviser().accounts({ payer: payer.publicKey, adviser: adviserPda }).signers([payer]).rpc();
+
+      const adviserBefore = await program.account.adviser.fetch(adviserPda);
+      const iteration5Before = await program.account.iteration.fetch(iteration5Pda);
+      const presaleBefore = await program.account.presale.fetch(presalePda);
+      await buy(joe_adviser_code);
+
+      const iteration5 = await program.account.iteration.fetch(iteration5Pda);
+      const buyer = await program.account.buyer.fetch(userPda);
+      const adviserAfter = await program.account.adviser.fetch(adviserPda);
+      const presale = await program.account.presale.fetch(presalePda);
+      const precision = new anchor.BN(1000000000);
+      const tokenAmount = new anchor.BN(144).mul(precision).mul(precision).div(iteration5.price);
+      const discountAmount = tokenAmount.mul(discount).div(precision);
+      expect(buyer.balance.toString()).to.equal(tokenAmount.add(discountAmount).toString());
+
+      // The discount is paid out of the adviser token share and not out of the iteration supply
+      const tPercent = anchor.BN.max(presale.tPercent, adviserBefore.tPercent);
+      const adviserTokenReward = tokenAmount.mul(tPercent).div(precision);
+      expect(adviserAfter.tokenReward.sub(adviserBefore.tokenReward).toString()).to.equal(adviserTokenReward.sub(discountAmount).toString());
+      expect(iteration5.sold.sub(iteration5Before.sold).toString()).to.equal(tokenAmount.toString());
+      // Adviser tokens are released from the vault alongside the bought ones
+      expect(presale.totalReleased.sub(presaleBefore.totalReleased).toString()).to.equal(tokenAmount.add(adviserTokenReward).toString());
+
+      await program.methods.setAdviserBuyerDiscount(new anchor.BN(0)).accounts({ payer: payer.publicKey, adviser: adviserPda }).signers([payer]).rpc();
+    });
+
+    it('should move unsold supply to the reserve when closing an iteration', async () => {
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [iteration5Pda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        ROUND_TAG, Buffer.from('_'), i16ToBytesLE(5)
+      ], program.programId);
+
+      await program.methods.setPresaleLeftoverPolicy({ reserve: {} }).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+      await program.methods.closeIteration().accounts({ payer: payer.publicKey, iteration: iteration5Pda, presale: presalePda }).signers([payer]).rpc();
+
+      const presale = await program.account.presale.fetch(presalePda);
+      const iteration5 = await program.account.iteration.fetch(iteration5Pda);
+      expect(presale.reserved.toString()).to.equal(iteration5.total.sub(iteration5.sold).toString());
+    });
+
+    it('should not be able to set soft cap after the presale is open', async () => {
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      try {
+        await program.methods.setPresaleSoftCap(new anchor.BN('1000000000000')).accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+        expect.fail('should have failed');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Presale already open');
+      }
+    });
+
+    it('should be able to init sol escrow', async () => {
+      let [escrowPda,] = anchor.web3.PublicKey.findProgramAddressSync([ESCROW_TAG], program.programId);
+      await program.methods.initSolEscrow().accounts({ payer: payer.publicKey }).signers([payer]).rpc();
+
+      const escrow = await program.account.escrow.fetch(escrowPda);
+      expect(escrow.deposited.toNumber()).to.equal(0);
+    });
+
+    it('should not be able to set token mint if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 9);
+
+      try {
+        await program.methods
+          .setTokenMint()
+          .accounts({ payer: payer.publicKey, tokenMint: tokenMint })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to set token mint', async () => {
+      tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 9);
+      await program.methods
+        .setTokenMint()
+        .accounts({ payer: payer.publicKey, tokenMint: tokenMint })
+        .signers([payer])
+        .rpc();
+
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [vaultPda,] = anchor.web3.PublicKey.findProgramAddressSync([VAULT_TAG], program.programId);
+      await mintTo(provider.connection, payer, tokenMint, vaultPda, payer, 1000000000000000000n);
+
+      const presale = await program.account.presale.fetch(presalePda);
+      const vault = await getAccount(provider.connection, vaultPda);
+      expect(presale.tokenMint.toString()).to.equal(tokenMint.toString());
+      expect(vault.owner.toString()).to.equal(presalePda.toString());
+    });
+
+    it('should be able to withdraw the reserved supply', async () => {
+      let [presalePda,] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      let [vaultPda,] = anchor.web3.PublicKey.findProgramAddressSync([VAULT_TAG], program.programId);
+      const storeAta = await getOrCreateAssociatedTokenAccount(provider.connection, payer, tokenMint, store);
+
+      try {
+        await program.methods
+          .burnLeftover()
+          .accounts({ payer: payer.publicKey, tokenMint: tokenMint })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Nothing to settle');
+      }
+
+      const vaultBefore = await getAccount(provider.connection, vaultPda);
+      await program.methods
+        .withdrawReserve()
+        .accounts({ payer: payer.publicKey, tokenMint: tokenMint, storeAta: storeAta.address })
+        .signers([payer])
+        .rpc();
+
+      const presale = await program.account.presale.fetch(presalePda);
+      const vault = await getAccount(provider.connection, vaultPda);
+      const storeAccount = await getAccount(provider.connection, storeAta.address);
+      expect(presale.reserveWithdrawn.toString()).to.equal(presale.reserved.toString());
+      expect(storeAccount.amount.toString()).to.equal(presale.reserved.toString());
+      expect((vaultBefore.amount - vault.amount).toString()).to.equal(presale.reserved.toString());
+    });
+
+    it('should not be able to claim tokens before claims are enabled', async () => {
+      try {
+        await program.methods
+          .claimTokens()
+          .accounts({ payer: payer.publicKey, tokenMint: tokenMint })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Token claims not enabled');
+      }
+    });
+
+    it('should not be able to set vesting if Unauthorized Signer', async () => {
+      const payer = await generateKeypair();
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      const accounts = { payer: payer.publicKey, presale: presalePda };
+
+      try {
+        await program.methods
+          .setPresaleVesting(new anchor.BN(200000000), new anchor.BN(86400), new anchor.BN(30 * 86400))
+          .accounts(accounts)
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Unauthorized Signer');
+      }
+    });
+
+    it('should be able to set vesting', async () => {
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      const accounts = { payer: payer.publicKey, presale: presalePda };
+      const tgePercent = new anchor.BN(200000000); // 20%
+      const cliff = new anchor.BN(86400); // 1 day
+      const duration = new anchor.BN(30 * 86400); // 30 days
+
+      await program.methods
+        .setPresaleVesting(tgePercent, cliff, duration)
+        .accounts(accounts)
+        .signers([payer])
+        .rpc();
+
+      const presale = await program.account.presale.fetch(presalePda);
+      expect(presale.vesting.tgePercent.toString()).to.equal(tgePercent.toString());
+      expect(presale.vesting.cliff.toString()).to.equal(cliff.toString());
+      expect(presale.vesting.duration.toString()).to.equal(duration.toString());
+    });
+
+    it('should be able to enable claims', async () => {
+      let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);
+      await program.methods.enableClaims().accounts({ payer: payer.publicKey, presale: presalePda }).signers([payer]).rpc();
+
+      const presale = await program.account.presale.fetch(presalePda);
+      expect(presale.claimsEnabled).to.equal(true);
+    });
+
+    it('should be able to claim tokens', async () => {
+      let [userPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        USER_TAG, Buffer.from('_'), payer.publicKey.toBuffer()
+      ], program.programId);
+
+      await program.methods
+        .claimTokens()
+        .accounts({ payer: payer.publicKey, tokenMint: tokenMint })
+        .signers([payer])
+        .rpc();
+
+      const buyer = await program.account.buyer.fetch(userPda);
+      const buyerAta = await getAccount(provider.connection, getAssociatedTokenAddressSync(tokenMint, payer.publicKey));
+      const unlocked = buyer.balance.mul(new anchor.BN(200000000)).div(new anchor.BN(1000000000));
+      expect(buyer.claimed.toString()).to.equal(unlocked.toString());
+      expect(buyerAta.amount.toString()).to.equal(unlocked.toString());
+    });
+
+    it('should not be able to claim locked tokens before cliff', async () => {
+      try {
+        await program.methods
+          .claimTokens()
+          .accounts({ payer: payer.publicKey, tokenMint: tokenMint })
+          .signers([payer])
+          .rpc();
+        expect.fail('Expected action to throw an error');
+      } catch (err) {
+        expect(err.error.errorMessage).to.equal('Buyer no funds');
+      }
+    });
+
+    it('should be able to claim_token_reward adviser interest', async () => {
+      const deadline = Math.floor(new Date().getTime() / 1000) + 600; // 10m from now
+
+      const message = Uint8Array.from(Buffer.from(`${bob_adviser_code}${bob_adviser.publicKey}${deadline}`));
+      const signature: Uint8Array = await ed.sign(message, payer.secretKey.slice(0, 32));
+
+      let [adviserPda,] = anchor.web3.PublicKey.findProgramAddressSync([
+        REF_TAG, Buffer.from('_'), Buffer.from(bob_adviser_code)
+      ], program.programId);
+      const tokenReward = (await program.account.adviser.fetch(adviserPda)).tokenReward;
+
+      const tx = new anchor.web3.Transaction()
+        .add(
+          // Ed25519 instruction
+          anchor.web3.Ed25519Program.createInstructionWithPublicKey({
+            publicKey: payer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          })
+        )
+        .add(
+          await program.methods
+            .claimTokenReward(bob_adviser_code, new anchor.BN(deadline), Array.from(signature), 0)
+            .accounts({
+              payer: bob_adviser.publicKey,
+              adviser: adviserPda,
+              tokenMint: tokenMint,
+            })
+            .instruction()
+        );
+
+      const { lastValidBlockHeight, blockhash } = await provider.connection.getLatestBlockhash();
+      tx.lastValidBlockHeight = lastValidBlockHeight;
+      tx.recentBlockhash = blockhash;
+      tx.feePayer = bob_adviser.publicKey;
+
+      tx.sign(bob_adviser);
+      const hash = await provider.connection.sendRawTransaction(tx.serialize());
+      const confirmation = await provider.connection.confirmTransaction(hash, 'confirmed');
+      if (confirmation.value.err) {
+        throw confirmation.value.err;
+      }
+
+      const adviser = await program.account.adviser.fetch(adviserPda);
+      const tokenAccount = await getAccount(provider.connection, getAssociatedTokenAddressSync(tokenMint, bob_adviser.publicKey));
+      expect(tokenAccount.amount.toString()).to.equal(tokenReward.toString());
+      expect(adviser.tokenReward.toString()).to.equal('0');
+    });
+
     it('should not be able to close presale if Unauthorized Signer', async () => {
       const payer = await generateKeypair();
       let [presalePda, _] = anchor.web3.PublicKey.findProgramAddressSync([], program.programId);